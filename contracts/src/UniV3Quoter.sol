@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+interface IUniswapV3Pool {
+    function swap(
+        address recipient,
+        bool zeroForOne,
+        int256 amountSpecified,
+        uint160 sqrtPriceLimitX96,
+        bytes calldata data
+    ) external returns (int256 amount0, int256 amount1);
+}
+
+/// @notice Quotes Uniswap V3 swaps by executing them and reverting in the
+/// swap callback, before any token is paid to the pool.
+/// @dev Deployed at an arbitrary address in simulations only, the pool never
+/// checks the callback sender. Every quoting function reverts with
+/// `Error(string)` whose payload is `abi.encode(amount0Delta, amount1Delta)`.
+contract UniV3Quoter {
+    /// @dev `TickMath.MIN_SQRT_RATIO + 10`
+    uint160 internal constant MIN_SQRT_PRICE_LIMIT = 4295128749;
+    /// @dev `TickMath.MAX_SQRT_RATIO - 1`
+    uint160 internal constant MAX_SQRT_PRICE_LIMIT = 1461446703485210103287273052203988822378723970341;
+
+    receive() external payable {}
+
+    /// @notice Exact-input quote, the output is the negative delta.
+    function getAmountOut(address pool, bool zeroForOne, uint256 amountIn) external {
+        IUniswapV3Pool(pool).swap(address(1), zeroForOne, int256(amountIn), sqrtPriceLimit(zeroForOne), "");
+    }
+
+    /// @notice Exact-output quote, the input is the positive delta.
+    function getAmountIn(address pool, bool zeroForOne, uint256 amountOut) external {
+        IUniswapV3Pool(pool).swap(address(1), zeroForOne, -int256(amountOut), sqrtPriceLimit(zeroForOne), "");
+    }
+
+    function uniswapV3SwapCallback(int256 amount0Delta, int256 amount1Delta, bytes calldata) external pure {
+        revert(string(abi.encode(amount0Delta, amount1Delta)));
+    }
+
+    function sqrtPriceLimit(bool zeroForOne) internal pure returns (uint160) {
+        return zeroForOne ? MIN_SQRT_PRICE_LIMIT : MAX_SQRT_PRICE_LIMIT;
+    }
+}
@@ -1,11 +1,13 @@
-use std::cmp::min;
+use std::cmp::{max, min};
 
 use alloy::{
-    primitives::{Address, Bytes, U160, U256, aliases::U24},
+    primitives::{Address, Bytes, I256, U160, U256, aliases::U24},
     sol,
-    sol_types::{SolCall, SolValue},
+    sol_types::{Panic, Revert, SolCall, SolError, SolValue},
 };
 
+use crate::{error::SimError, uni_v3::V3Path};
+
 sol! {
     struct QuoteExactInputSingleParams {
         address tokenIn;
@@ -24,43 +26,230 @@ sol! {
         uint32 initializedTicksCrossed,
         uint256 gasEstimate
     );
+
+    struct QuoteExactOutputSingleParams {
+        address tokenIn;
+        address tokenOut;
+        uint256 amount;
+        uint24 fee;
+        uint160 sqrtPriceLimitX96;
+    }
+
+    function quoteExactOutputSingle(QuoteExactOutputSingleParams memory params)
+    public
+    override
+    returns (
+        uint256 amountIn,
+        uint160 sqrtPriceX96After,
+        uint32 initializedTicksCrossed,
+        uint256 gasEstimate
+    );
+
+    function quoteExactInput(bytes memory path, uint256 amountIn)
+    public
+    override
+    returns (
+        uint256 amountOut,
+        uint160[] memory sqrtPriceX96AfterList,
+        uint32[] memory initializedTicksCrossedList,
+        uint256 gasEstimate
+    );
+
+    function quoteExactOutput(bytes memory path, uint256 amountOut)
+    public
+    override
+    returns (
+        uint256 amountIn,
+        uint160[] memory sqrtPriceX96AfterList,
+        uint32[] memory initializedTicksCrossedList,
+        uint256 gasEstimate
+    );
 }
 
-pub fn decode_quote_response(response: Bytes) -> anyhow::Result<u128> {
-    let (amount_out, _, _, _) = <(u128, u128, u32, u128)>::abi_decode(&response)?;
-    Ok(amount_out)
+/// Result of a Quoter V2 quote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuoteResult {
+    pub amount_out: U256,
+    pub sqrt_price_x96_after: U160,
+    pub ticks_crossed: u32,
+    pub gas_estimate: U256,
+    /// The swap stopped at the price limit, so part of the amount in may be left.
+    ///
+    /// Every backend tells it from the price after the swap (the quoter doesn't return
+    /// the amount swapped), a swap that uses up the amount in right at the limit counts.
+    pub partial_fill: bool,
 }
 
-/// Returns bytes encoded calldata for the `quoteExactInputSingle` quoting function.
-pub fn quote_calldata(token_in: Address, token_out: Address, amount_in: U256, fee: u32) -> Bytes {
-    let zero_for_one = token_in < token_out;
+/// Decodes a quote made with the MIN/MAX price limit, see [`decode_quote_response_with_limit`].
+pub fn decode_quote_response(response: Bytes) -> Result<QuoteResult, SimError> {
+    decode_quote_response_with_limit(response, None)
+}
 
-    // The `sqrt_price_limit_x96` represents price limit that we are willing to pay.
-    // However, since our MEV statergy assumes that we will be the first to interact with
-    // pool in the new block, we use MIN/MAX values (depending on which token we exchange).
-    let sqrt_price_limit_x96: U160 = if zero_for_one {
-        "4295128749".parse().unwrap() // min value
-    } else {
-        "1461446703485210103287273052203988822378723970341" // max value
-            .parse()
-            .unwrap()
+/// Decodes a quote made with [`quote_calldata_with_limit`] and the same `sqrt_price_limit_x96`.
+///
+/// The quoter doesn't return the amount swapped, so a swap that stops at the limit
+/// is reported as a partial fill even if it used up the whole amount in.
+pub fn decode_quote_response_with_limit(
+    response: Bytes,
+    sqrt_price_limit_x96: Option<U160>,
+) -> Result<QuoteResult, SimError> {
+    let quote = quoteExactInputSingleCall::abi_decode_returns(&response)?;
+    let partial_fill = match sqrt_price_limit_x96 {
+        Some(limit) => quote.sqrtPriceX96After == limit,
+        // Only the bound in the swap direction can be reached.
+        None => [true, false].into_iter().any(|zero_for_one| {
+            quote.sqrtPriceX96After == default_sqrt_price_limit_x96(zero_for_one)
+        }),
     };
+    Ok(QuoteResult {
+        amount_out: quote.amountOut,
+        sqrt_price_x96_after: quote.sqrtPriceX96After,
+        ticks_crossed: quote.initializedTicksCrossed,
+        gas_estimate: quote.gasEstimate,
+        partial_fill,
+    })
+}
+
+/// Returns bytes encoded calldata for the `quoteExactInputSingle` quoting function.
+pub fn quote_calldata(token_in: Address, token_out: Address, amount_in: U256, fee: u32) -> Bytes {
+    quote_calldata_with_limit(token_in, token_out, amount_in, fee, None)
+}
 
+/// Same as [`quote_calldata`], but the swap stops once the price reaches `sqrt_price_limit_x96`.
+///
+/// `None` is the MIN/MAX price (depending on direction), see
+/// [`slippage_sqrt_price_limit`](crate::uni_v3::slippage_sqrt_price_limit) for a limit
+/// computed from the current price.
+pub fn quote_calldata_with_limit(
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+    fee: u32,
+    sqrt_price_limit_x96: Option<U160>,
+) -> Bytes {
     let params = QuoteExactInputSingleParams {
         tokenIn: token_in,
         tokenOut: token_out,
         amountIn: amount_in,
         fee: U24::from(fee),
-        sqrtPriceLimitX96: sqrt_price_limit_x96,
+        sqrtPriceLimitX96: sqrt_price_limit_x96
+            .unwrap_or_else(|| default_sqrt_price_limit_x96(token_in < token_out)),
     };
 
     Bytes::from(quoteExactInputSingleCall { params }.abi_encode())
 }
 
-sol! {
-    function getAmountOut(address pool, bool zeroForOne, uint256 amountIn) external;
+/// Result of a Quoter V2 exact-output quote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExactOutputQuoteResult {
+    pub amount_in: U256,
+    pub sqrt_price_x96_after: U160,
+    pub ticks_crossed: u32,
+    pub gas_estimate: U256,
 }
 
+/// Returns bytes encoded calldata for the `quoteExactOutputSingle` quoting function.
+pub fn quote_exact_output_single_calldata(
+    token_in: Address,
+    token_out: Address,
+    amount_out: U256,
+    fee: u32,
+) -> Bytes {
+    let params = QuoteExactOutputSingleParams {
+        tokenIn: token_in,
+        tokenOut: token_out,
+        amount: amount_out,
+        fee: U24::from(fee),
+        sqrtPriceLimitX96: default_sqrt_price_limit_x96(token_in < token_out),
+    };
+
+    Bytes::from(quoteExactOutputSingleCall { params }.abi_encode())
+}
+
+pub fn decode_quote_exact_output_single_response(
+    response: Bytes,
+) -> Result<ExactOutputQuoteResult, SimError> {
+    let quote = quoteExactOutputSingleCall::abi_decode_returns(&response)?;
+    Ok(ExactOutputQuoteResult {
+        amount_in: quote.amountIn,
+        sqrt_price_x96_after: quote.sqrtPriceX96After,
+        ticks_crossed: quote.initializedTicksCrossed,
+        gas_estimate: quote.gasEstimate,
+    })
+}
+
+/// Result of a Quoter V2 multi-hop quote, one price and tick count per pool.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathQuoteResult {
+    /// Amount out of `quoteExactInput`, amount in of `quoteExactOutput`.
+    pub amount: U256,
+    pub sqrt_price_x96_after: Vec<U160>,
+    pub ticks_crossed: Vec<u32>,
+    pub gas_estimate: U256,
+}
+
+/// Returns bytes encoded calldata for the multi-hop `quoteExactInput` quoting function.
+pub fn quote_exact_input_calldata(path: &V3Path, amount_in: U256) -> Bytes {
+    Bytes::from(
+        quoteExactInputCall {
+            path: path.encode(),
+            amountIn: amount_in,
+        }
+        .abi_encode(),
+    )
+}
+
+pub fn decode_quote_exact_input_response(response: Bytes) -> Result<PathQuoteResult, SimError> {
+    let quote = quoteExactInputCall::abi_decode_returns(&response)?;
+    Ok(PathQuoteResult {
+        amount: quote.amountOut,
+        sqrt_price_x96_after: quote.sqrtPriceX96AfterList,
+        ticks_crossed: quote.initializedTicksCrossedList,
+        gas_estimate: quote.gasEstimate,
+    })
+}
+
+/// Returns bytes encoded calldata for the multi-hop `quoteExactOutput` quoting function.
+///
+/// `path` is in swap order, it is reversed for the quoter.
+pub fn quote_exact_output_calldata(path: &V3Path, amount_out: U256) -> Bytes {
+    Bytes::from(
+        quoteExactOutputCall {
+            path: path.reversed().encode(),
+            amountOut: amount_out,
+        }
+        .abi_encode(),
+    )
+}
+
+pub fn decode_quote_exact_output_response(response: Bytes) -> Result<PathQuoteResult, SimError> {
+    let quote = quoteExactOutputCall::abi_decode_returns(&response)?;
+    Ok(PathQuoteResult {
+        amount: quote.amountIn,
+        sqrt_price_x96_after: quote.sqrtPriceX96AfterList,
+        ticks_crossed: quote.initializedTicksCrossedList,
+        gas_estimate: quote.gasEstimate,
+    })
+}
+
+/// The price limit of the quotes made without one, MIN/MAX depending on the direction.
+pub fn default_sqrt_price_limit_x96(zero_for_one: bool) -> U160 {
+    // The `sqrt_price_limit_x96` represents price limit that we are willing to pay.
+    // By default our MEV statergy assumes that we will be the first to interact with
+    // pool in the new block, so we use MIN/MAX values (depending on which token we exchange).
+    if zero_for_one {
+        "4295128749".parse().unwrap() // min value
+    } else {
+        "1461446703485210103287273052203988822378723970341" // max value
+            .parse()
+            .unwrap()
+    }
+}
+
+// The custom quoter deployed at `CUSTOM_QUOTER_ADDR`.
+sol!("contracts/src/UniV3Quoter.sol");
+
+/// Returns bytes encoded calldata for the custom quoter exact-input `getAmountOut` function.
 pub fn get_amount_out_calldata(
     pool: Address,
     token_in: Address,
@@ -68,7 +257,7 @@ pub fn get_amount_out_calldata(
     amount_in: U256,
 ) -> Bytes {
     Bytes::from(
-        getAmountOutCall {
+        UniV3Quoter::getAmountOutCall {
             pool,
             zeroForOne: token_in < token_out,
             amountIn: amount_in,
@@ -77,11 +266,397 @@ pub fn get_amount_out_calldata(
     )
 }
 
-pub fn decode_get_amount_out_response(response: Bytes) -> anyhow::Result<u128> {
-    let value = response.to_vec();
-    let last_64_bytes = &value[value.len() - 64..];
-    let (a, b) = <(i128, i128)>::abi_decode(last_64_bytes)?;
-    let value_out = min(a, b);
-    let value_out = -value_out;
-    Ok(value_out as u128)
+/// Returns bytes encoded calldata for the custom quoter exact-output `getAmountIn` function.
+///
+/// Only available in the quoter built from source, see `BytecodeRegistry::with_foundry_out`.
+pub fn get_amount_in_calldata(
+    pool: Address,
+    token_in: Address,
+    token_out: Address,
+    amount_out: U256,
+) -> Bytes {
+    Bytes::from(
+        UniV3Quoter::getAmountInCall {
+            pool,
+            zeroForOne: token_in < token_out,
+            amountOut: amount_out,
+        }
+        .abi_encode(),
+    )
+}
+
+/// Swap deltas the custom quoter reverts with, from the pool's point of view:
+/// the positive amount is paid to the pool, the negative one is paid by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapDeltas {
+    pub amount0: I256,
+    pub amount1: I256,
+}
+
+impl SwapDeltas {
+    /// True if token0 is paid to the pool.
+    pub fn zero_for_one(&self) -> bool {
+        self.amount0.is_positive()
+    }
+
+    pub fn amount_in(&self) -> U256 {
+        max(self.amount0, self.amount1).into_raw()
+    }
+
+    pub fn amount_out(&self) -> U256 {
+        min(self.amount0, self.amount1).unsigned_abs()
+    }
+}
+
+/// Decodes the swap deltas the custom quoter reverts with: an `Error(string)`
+/// whose payload is `abi.encode(amount0Delta, amount1Delta)`.
+///
+/// Any other revert, e.g. a failed swap, is returned as [`SimError::UnexpectedRevert`]
+/// with the decoded `Error(string)` message, `Panic(uint256)` kind or custom error selector.
+/// Payloads too short for a selector or not matching it fail with [`SimError::Abi`].
+pub fn decode_quoter_revert(output: &Bytes) -> Result<SwapDeltas, SimError> {
+    let unexpected = |reason: String| SimError::UnexpectedRevert {
+        output: output.clone(),
+        reason,
+    };
+
+    let Some((selector, data)) = output.split_first_chunk::<4>() else {
+        return Err(alloy::sol_types::Error::Overrun.into());
+    };
+
+    match *selector {
+        Revert::SELECTOR => {
+            let payload = Bytes::abi_decode(data)?;
+            if payload.len() == 64 {
+                let (amount0, amount1) = <(I256, I256)>::abi_decode_params(&payload)?;
+                let deltas = SwapDeltas { amount0, amount1 };
+                // One side is paid to the pool, the other (maybe nothing) is paid by it.
+                if max(amount0, amount1).is_positive() && !min(amount0, amount1).is_positive() {
+                    return Ok(deltas);
+                }
+            }
+            Err(unexpected(String::from_utf8_lossy(&payload).into_owned()))
+        }
+        Panic::SELECTOR => {
+            let panic = Panic::abi_decode(output)?;
+            let reason = match panic.kind() {
+                Some(kind) => format!("panic: {kind}"),
+                None => format!("panic: code {}", panic.code),
+            };
+            Err(unexpected(reason))
+        }
+        selector => Err(unexpected(format!(
+            "custom error 0x{}",
+            alloy::hex::encode(selector)
+        ))),
+    }
+}
+
+/// Returns the amount paid by the pool, the negative delta.
+pub fn decode_get_amount_out_response(response: Bytes) -> Result<U256, SimError> {
+    Ok(decode_quoter_revert(&response)?.amount_out())
+}
+
+/// Returns the amount paid to the pool, the positive delta.
+pub fn decode_get_amount_in_response(response: Bytes) -> Result<U256, SimError> {
+    Ok(decode_quoter_revert(&response)?.amount_in())
+}
+
+sol! {
+    function getReserves()
+    external
+    view
+    returns (uint112 reserve0, uint112 reserve1, uint32 blockTimestampLast);
+}
+
+/// Returns bytes encoded calldata for the V2 pair `getReserves` function.
+pub fn get_reserves_calldata() -> Bytes {
+    Bytes::from(getReservesCall {}.abi_encode())
+}
+
+/// Decodes `(reserve0, reserve1)` from the `getReserves` response.
+pub fn decode_get_reserves_response(response: Bytes) -> Result<(U256, U256), SimError> {
+    let reserves = getReservesCall::abi_decode_returns(&response)?;
+    Ok((U256::from(reserves.reserve0), U256::from(reserves.reserve1)))
+}
+
+sol! {
+    function balanceOf(address account) external view returns (uint256);
+}
+
+/// Returns bytes encoded calldata for the ERC20 `balanceOf` function.
+pub fn balance_of_calldata(account: Address) -> Bytes {
+    Bytes::from(balanceOfCall { account }.abi_encode())
+}
+
+pub fn decode_balance_of_response(response: Bytes) -> Result<U256, SimError> {
+    Ok(balanceOfCall::abi_decode_returns(&response)?)
+}
+
+sol! {
+    function allowance(address owner, address spender) external view returns (uint256);
+}
+
+/// Returns bytes encoded calldata for the ERC20 `allowance` function.
+pub fn allowance_calldata(owner: Address, spender: Address) -> Bytes {
+    Bytes::from(allowanceCall { owner, spender }.abi_encode())
+}
+
+pub fn decode_allowance_response(response: Bytes) -> Result<U256, SimError> {
+    Ok(allowanceCall::abi_decode_returns(&response)?)
+}
+
+sol! {
+    function implementation() external view returns (address);
+}
+
+/// Returns bytes encoded calldata for the beacon `implementation` function.
+pub fn implementation_calldata() -> Bytes {
+    Bytes::from(implementationCall {}.abi_encode())
+}
+
+pub fn decode_implementation_response(response: Bytes) -> Result<Address, SimError> {
+    Ok(implementationCall::abi_decode_returns(&response)?)
+}
+
+sol! {
+    function getPool(address tokenA, address tokenB, uint24 fee) external view returns (address pool);
+}
+
+/// Returns bytes encoded calldata for the V3 factory `getPool` function.
+pub fn get_pool_calldata(token_a: Address, token_b: Address, fee: u32) -> Bytes {
+    Bytes::from(
+        getPoolCall {
+            tokenA: token_a,
+            tokenB: token_b,
+            fee: U24::from(fee),
+        }
+        .abi_encode(),
+    )
+}
+
+/// Returns the pool address, zero if there is no pool for the pair and fee tier.
+pub fn decode_get_pool_response(response: Bytes) -> Result<Address, SimError> {
+    Ok(getPoolCall::abi_decode_returns(&response)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_string(payload: &[u8]) -> Bytes {
+        [
+            &Revert::SELECTOR[..],
+            &Bytes::copy_from_slice(payload).abi_encode(),
+        ]
+        .concat()
+        .into()
+    }
+
+    fn unexpected_reason(output: &Bytes) -> String {
+        match decode_quoter_revert(output) {
+            Err(SimError::UnexpectedRevert { reason, .. }) => reason,
+            result => panic!("expected an unexpected revert, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn decodes_deltas_wrapped_in_error_string() {
+        let amount0 = I256::try_from(1_000_000_000_000_000_000_u128).unwrap();
+        let amount1 = -I256::try_from(2_500_000_000_u64).unwrap();
+        let output = error_string(&(amount0, amount1).abi_encode_params());
+
+        let deltas = decode_quoter_revert(&output).unwrap();
+        assert_eq!(deltas, SwapDeltas { amount0, amount1 });
+        assert!(deltas.zero_for_one());
+        assert_eq!(deltas.amount_in(), amount0.into_raw());
+        assert_eq!(deltas.amount_out(), U256::from(2_500_000_000_u64));
+        assert_eq!(
+            decode_get_amount_out_response(output).unwrap(),
+            U256::from(2_500_000_000_u64)
+        );
+    }
+
+    #[test]
+    fn rejects_deltas_paid_both_ways() {
+        let amount = I256::try_from(1_000_u64).unwrap();
+        let output = error_string(&(amount, amount).abi_encode_params());
+        assert!(matches!(
+            decode_quoter_revert(&output),
+            Err(SimError::UnexpectedRevert { .. })
+        ));
+    }
+
+    #[test]
+    fn returns_plain_error_string_reason() {
+        let output: Bytes = Revert::from("SPL").abi_encode().into();
+        assert_eq!(unexpected_reason(&output), "SPL");
+    }
+
+    #[test]
+    fn returns_panic_kind() {
+        let output: Bytes = Panic::from(0x11).abi_encode().into();
+        assert!(unexpected_reason(&output).starts_with("panic: "));
+    }
+
+    #[test]
+    fn returns_custom_error_selector() {
+        let output = Bytes::from_static(&[0xde, 0xad, 0xbe, 0xef, 0x00, 0x01]);
+        assert_eq!(unexpected_reason(&output), "custom error 0xdeadbeef");
+    }
+
+    #[test]
+    fn fails_to_decode_short_or_garbage_payloads() {
+        for output in [
+            Bytes::new(),
+            Bytes::from_static(&[0x08, 0xc3, 0x79]),
+            [&Revert::SELECTOR[..], &[0xff; 7]].concat().into(),
+            [&Panic::SELECTOR[..], &[0x01]].concat().into(),
+        ] {
+            assert!(
+                matches!(decode_quoter_revert(&output), Err(SimError::Abi(_))),
+                "{output}"
+            );
+        }
+    }
+
+    const WETH: Address = Address::repeat_byte(0xee);
+    const USDC: Address = Address::repeat_byte(0x0c);
+    const DAI: Address = Address::repeat_byte(0xda);
+
+    /// The 32-byte ABI word at `index`, after the selector.
+    fn word(calldata: &[u8], index: usize) -> &[u8] {
+        &calldata[4 + 32 * index..4 + 32 * (index + 1)]
+    }
+
+    fn address_word(address: Address) -> [u8; 32] {
+        address.into_word().0
+    }
+
+    fn weth_usdc_dai() -> V3Path {
+        V3Path::builder(WETH)
+            .hop(500, USDC)
+            .hop(100, DAI)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn encodes_exact_output_single() {
+        let calldata = quote_exact_output_single_calldata(USDC, WETH, U256::from(5_000), 500);
+
+        // quoteExactOutputSingle((address,address,uint256,uint24,uint160))
+        assert_eq!(calldata[..4], [0xbd, 0x21, 0x70, 0x4a]);
+        // A static tuple is encoded in place.
+        assert_eq!(calldata.len(), 4 + 5 * 32);
+        assert_eq!(word(&calldata, 0), address_word(USDC));
+        assert_eq!(word(&calldata, 1), address_word(WETH));
+        assert_eq!(word(&calldata, 2), U256::from(5_000).to_be_bytes::<32>());
+        assert_eq!(word(&calldata, 3), U256::from(500).to_be_bytes::<32>());
+        // USDC < WETH, so the price goes down to the MIN limit.
+        assert_eq!(
+            word(&calldata, 4),
+            U256::from(default_sqrt_price_limit_x96(true)).to_be_bytes::<32>()
+        );
+
+        let params = quoteExactOutputSingleCall::abi_decode(&calldata)
+            .unwrap()
+            .params;
+        assert_eq!(params.amount, U256::from(5_000));
+        assert_eq!(params.fee, U24::from(500));
+    }
+
+    #[test]
+    fn decodes_exact_output_single() {
+        let sqrt_price_x96_after = U160::from(1) << 96;
+        let response: Bytes = (
+            U256::from(1_234),
+            sqrt_price_x96_after,
+            2_u32,
+            U256::from(80_000),
+        )
+            .abi_encode_params()
+            .into();
+
+        assert_eq!(
+            decode_quote_exact_output_single_response(response).unwrap(),
+            ExactOutputQuoteResult {
+                amount_in: U256::from(1_234),
+                sqrt_price_x96_after,
+                ticks_crossed: 2,
+                gas_estimate: U256::from(80_000),
+            }
+        );
+        assert!(matches!(
+            decode_quote_exact_output_single_response(Bytes::from_static(&[0; 32])),
+            Err(SimError::Abi(_))
+        ));
+    }
+
+    #[test]
+    fn encodes_exact_input_path() {
+        let path = weth_usdc_dai();
+        let calldata = quote_exact_input_calldata(&path, U256::from(7));
+
+        // quoteExactInput(bytes,uint256)
+        assert_eq!(calldata[..4], [0xcd, 0xca, 0x17, 0x53]);
+        // Offset of the path, the amount, then the path length and its padded bytes.
+        assert_eq!(word(&calldata, 0), U256::from(64).to_be_bytes::<32>());
+        assert_eq!(word(&calldata, 1), U256::from(7).to_be_bytes::<32>());
+        assert_eq!(word(&calldata, 2), U256::from(66).to_be_bytes::<32>());
+        assert_eq!(calldata.len(), 4 + 3 * 32 + 96);
+        assert_eq!(calldata[4 + 96..4 + 96 + 66], path.encode()[..]);
+
+        let call = quoteExactInputCall::abi_decode(&calldata).unwrap();
+        assert_eq!(V3Path::decode(&call.path).unwrap(), path);
+        assert_eq!(call.amountIn, U256::from(7));
+    }
+
+    #[test]
+    fn encodes_exact_output_path_reversed() {
+        let path = weth_usdc_dai();
+        let calldata = quote_exact_output_calldata(&path, U256::from(9));
+
+        // quoteExactOutput(bytes,uint256)
+        assert_eq!(calldata[..4], [0x2f, 0x80, 0xbb, 0x1d]);
+        let call = quoteExactOutputCall::abi_decode(&calldata).unwrap();
+        assert_eq!(call.amountOut, U256::from(9));
+        // The quoter walks the path from the token out.
+        assert_eq!(call.path[..20], DAI[..]);
+        assert_eq!(V3Path::decode(&call.path).unwrap(), path.reversed());
+    }
+
+    #[test]
+    fn decodes_path_quotes() {
+        let prices = vec![U160::from(11), U160::from(22)];
+        let ticks = vec![1_u32, 0];
+        let response: Bytes = (
+            U256::from(1_000),
+            prices.clone(),
+            ticks.clone(),
+            U256::from(150_000),
+        )
+            .abi_encode_params()
+            .into();
+        let expected = PathQuoteResult {
+            amount: U256::from(1_000),
+            sqrt_price_x96_after: prices,
+            ticks_crossed: ticks,
+            gas_estimate: U256::from(150_000),
+        };
+
+        assert_eq!(
+            decode_quote_exact_input_response(response.clone()).unwrap(),
+            expected
+        );
+        assert_eq!(
+            decode_quote_exact_output_response(response.clone()).unwrap(),
+            expected
+        );
+        // The arrays are dynamic, a response cut before their contents fails.
+        assert!(matches!(
+            decode_quote_exact_input_response(response.slice(..128)),
+            Err(SimError::Abi(_))
+        ));
+    }
 }
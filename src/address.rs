@@ -2,11 +2,16 @@ use alloy::primitives::{Address, address};
 
 pub static WETH_ADDR: Address = address!("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
 pub static USDC_ADDR: Address = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+pub static DAI_ADDR: Address = address!("6b175474e89094c44da98b954eedeac495271d0f");
 
+pub static V3_FACTORY_ADDR: Address = address!("1F98431c8aD98523631AE4a59f267346ea31F984");
 pub static V3_QUOTER_ADDR: Address = address!("61fFE014bA17989E743c5F6cB21bF9697530B21e");
 pub static V3_POOL_500_ADDR: Address = address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640");
 pub static V3_POOL_3000_ADDR: Address = address!("8ad599c3A0ff1De082011EFDDc58f1908eb6e6D8");
 
+pub static V2_PAIR_ADDR: Address = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+pub static SUSHI_PAIR_ADDR: Address = address!("397FF1542f962076d0BFE58eA045FfA2d347ACa0");
+
 pub static ME: Address = address!("0000000000000000000000000000000000000001");
 
 pub static CUSTOM_QUOTER_ADDR: Address = address!("A5C381211A406b48A073E954e6949B0D49506bc0");
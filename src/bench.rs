@@ -0,0 +1,269 @@
+//! Latency measurements of quote backends.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    quote::{QuoteBackend, QuoteRequest},
+    rpc::{RpcStats, RpcSummary},
+};
+
+/// Latency distribution of single quotes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub min: Duration,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl LatencyStats {
+    pub fn from_samples(mut samples: Vec<Duration>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort();
+
+        let total: Duration = samples.iter().sum();
+        Self {
+            samples: samples.len(),
+            min: samples[0],
+            mean: total / samples.len() as u32,
+            p50: percentile(&samples, 50),
+            p90: percentile(&samples, 90),
+            p99: percentile(&samples, 99),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted samples.
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    let rank = (percent * sorted.len()).div_ceil(100);
+    sorted[rank.saturating_sub(1)]
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BenchRun {
+    pub latency: LatencyStats,
+    /// Requests sent during the measured runs, empty without stats.
+    pub rpc: RpcSummary,
+}
+
+/// Quotes all the requests `warmup` times unmeasured, then `runs` times
+/// timing every quote on its own.
+///
+/// `stats` should be the ones of the backend's provider, they are reset after the warmup.
+pub async fn bench_quotes<B: QuoteBackend>(
+    backend: &B,
+    requests: &[QuoteRequest],
+    warmup: usize,
+    runs: usize,
+    stats: Option<&RpcStats>,
+) -> anyhow::Result<BenchRun> {
+    for _ in 0..warmup {
+        for request in requests {
+            backend.quote(*request).await?;
+        }
+    }
+    if let Some(stats) = stats {
+        stats.reset();
+    }
+
+    let mut samples = Vec::with_capacity(runs * requests.len());
+    for _ in 0..runs {
+        for request in requests {
+            let start = Instant::now();
+            backend.quote(*request).await?;
+            samples.push(start.elapsed());
+        }
+    }
+
+    Ok(BenchRun {
+        latency: LatencyStats::from_samples(samples),
+        rpc: stats.map(RpcStats::reset).unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::Arc,
+        task::{Context, Poll},
+    };
+
+    use alloy::{
+        eips::BlockId,
+        primitives::{Address, Bytes, U256},
+        providers::ProviderBuilder,
+        rpc::{
+            client::ClientBuilder,
+            json_rpc::{
+                ErrorPayload, RequestPacket, Response, ResponsePacket, ResponsePayload,
+                SerializedRequest,
+            },
+        },
+        transports::{TransportError, TransportFut},
+    };
+    use serde_json::value::RawValue;
+    use tower::Service;
+
+    use super::*;
+    use crate::{
+        cache::EvmCache,
+        quote::RevmBackend,
+        revm::{RevmProvider, Simulator, init_account, init_cache_db},
+    };
+
+    const QUOTER: Address = Address::repeat_byte(0x42);
+
+    /// Node with the code of a few contracts and nothing else: zero balances,
+    /// nonces and storage everywhere.
+    #[derive(Clone)]
+    struct FakeNode {
+        code: Arc<HashMap<Address, Bytes>>,
+    }
+
+    impl FakeNode {
+        fn result(&self, request: &SerializedRequest) -> ResponsePayload {
+            let result = match request.method() {
+                "eth_getCode" => {
+                    let (address, _): (Address, BlockId) =
+                        serde_json::from_str(request.params().unwrap().get()).unwrap();
+                    let code = self.code.get(&address).cloned().unwrap_or_default();
+                    serde_json::to_string(&code).unwrap()
+                }
+                "eth_getBalance" | "eth_getTransactionCount" | "eth_getStorageAt" => {
+                    "\"0x0\"".to_string()
+                }
+                _ => return ResponsePayload::Failure(ErrorPayload::method_not_found()),
+            };
+            ResponsePayload::Success(RawValue::from_string(result).unwrap())
+        }
+    }
+
+    impl Service<RequestPacket> for FakeNode {
+        type Response = ResponsePacket;
+        type Error = TransportError;
+        type Future = TransportFut<'static>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: RequestPacket) -> Self::Future {
+            let mut responses: Vec<_> = request
+                .requests()
+                .iter()
+                .map(|request| Response {
+                    id: request.id().clone(),
+                    payload: self.result(request),
+                })
+                .collect();
+            let response = match request {
+                RequestPacket::Single(_) => ResponsePacket::Single(responses.remove(0)),
+                RequestPacket::Batch(_) => ResponsePacket::Batch(responses),
+            };
+            Box::pin(async move { Ok(response) })
+        }
+    }
+
+    fn fake_provider(stats: RpcStats) -> RevmProvider {
+        // Returns `amountIn` as `amountOut`, zeros for the rest of the Quoter V2 response.
+        let quoter = Bytes::from_static(&[
+            0x60, 0x44, 0x35, 0x60, 0x00, 0x52, 0x60, 0x80, 0x60, 0x00, 0xf3,
+        ]);
+        let node = FakeNode {
+            code: Arc::new(HashMap::from([(QUOTER, quoter)])),
+        };
+        let client = ClientBuilder::default().layer(stats).transport(node, true);
+        Arc::new(ProviderBuilder::new().connect_client(client))
+    }
+
+    /// `RevmBackend::prewarmed` without the pools: the quoter is preloaded,
+    /// its code cached on disk.
+    async fn cached_backend(provider: RevmProvider, cache: EvmCache) -> RevmBackend {
+        let mut cache_db = init_cache_db(provider.clone(), BlockId::number(1), cache);
+        init_account(QUOTER, &mut cache_db, provider).await.unwrap();
+        RevmBackend::new(Simulator::new(cache_db)).with_quoter(QUOTER)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn cached_backend_fetches_no_code_after_warmup() {
+        let dir = std::env::temp_dir().join(format!("denegnet-bench-{}", std::process::id()));
+        let cache = EvmCache::new(&dir, 1);
+        let requests: Vec<_> = [1_u64, 2, 3]
+            .into_iter()
+            .map(|amount| QuoteRequest::new(Address::ZERO, QUOTER, 500, U256::from(amount)))
+            .collect();
+
+        let stats = RpcStats::new();
+        let backend = cached_backend(fake_provider(stats.clone()), cache.clone()).await;
+        let run = bench_quotes(&backend, &requests, 1, 2, Some(&stats.scoped()))
+            .await
+            .unwrap();
+        assert_eq!(run.latency.samples, 6);
+        assert_eq!(run.rpc.count("eth_getCode"), 0);
+        assert_eq!(run.rpc.total(), 0);
+        // The quoter, the caller and the beneficiary were fetched before.
+        assert_eq!(stats.count("eth_getCode"), 3);
+
+        // A new backend at the same block gets all of it from the disk cache.
+        let stats = RpcStats::new();
+        let backend = cached_backend(fake_provider(stats.clone()), cache).await;
+        let quote = backend.quote(requests[2]).await.unwrap();
+        assert_eq!(quote.amount_out, U256::from(3));
+        assert_eq!(stats.count("eth_getCode"), 0);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn millis(samples: impl IntoIterator<Item = u64>) -> Vec<Duration> {
+        samples.into_iter().map(Duration::from_millis).collect()
+    }
+
+    #[test]
+    fn computes_nearest_rank_percentiles() {
+        // 1..=100 ms, out of order.
+        let samples = millis((1..=100).rev());
+        let stats = LatencyStats::from_samples(samples);
+
+        assert_eq!(stats.samples, 100);
+        assert_eq!(stats.min, Duration::from_millis(1));
+        assert_eq!(stats.mean, Duration::from_micros(50_500));
+        assert_eq!(stats.p50, Duration::from_millis(50));
+        assert_eq!(stats.p90, Duration::from_millis(90));
+        assert_eq!(stats.p99, Duration::from_millis(99));
+        assert_eq!(stats.max, Duration::from_millis(100));
+
+        // With few samples the high percentiles are the slowest one.
+        let stats = LatencyStats::from_samples(millis([30, 10, 20, 40]));
+        assert_eq!(stats.p50, Duration::from_millis(20));
+        assert_eq!(stats.p90, Duration::from_millis(40));
+        assert_eq!(stats.p99, Duration::from_millis(40));
+        assert_eq!(stats.mean, Duration::from_millis(25));
+
+        let stats = LatencyStats::from_samples(millis([7]));
+        assert_eq!(
+            (stats.min, stats.p50, stats.p99),
+            (
+                Duration::from_millis(7),
+                Duration::from_millis(7),
+                Duration::from_millis(7)
+            )
+        );
+    }
+
+    #[test]
+    fn empty_samples_are_zero() {
+        let stats = LatencyStats::from_samples(Vec::new());
+        assert_eq!(stats.samples, 0);
+        assert_eq!(stats.mean, Duration::ZERO);
+        assert_eq!(stats.p50, Duration::ZERO);
+        assert_eq!(stats.p99, Duration::ZERO);
+        assert_eq!(stats.max, Duration::ZERO);
+    }
+}
@@ -0,0 +1,392 @@
+use std::{collections::BTreeSet, ops::Div, str::FromStr, time::Instant};
+
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, I256, U256},
+};
+use anyhow::ensure;
+use clap::{Args, ValueEnum};
+use denegnet::{
+    address::{
+        CUSTOM_QUOTER_ADDR, ME, SUSHI_PAIR_ADDR, USDC_ADDR, V2_PAIR_ADDR, V3_POOL_500_ADDR,
+        V3_POOL_3000_ADDR, WETH_ADDR,
+    },
+    bytecode::BytecodeRegistry,
+    cache::EvmCache,
+    constant::ONE_ETHER,
+    erc20::set_erc20_balance,
+    optimize::maximize_profit,
+    path::{Backend, GasPricing, Hop, Path, Protocol},
+    revm::{
+        AlloyCacheDB, Simulator, fetch_block_env, fetch_priority_fee, init_account,
+        init_account_with_bytecode, init_cache_db, init_proxy_implementation,
+    },
+    uni_v2::UNI_V2_FEE_NUMERATOR,
+};
+use serde_json::json;
+
+use crate::{Context, Format, parse_token, token_name};
+
+/// Stop the search once the optimal input is known within 1 gwei.
+const TOLERANCE_WEI: u64 = 1_000_000_000;
+
+#[derive(Args)]
+pub struct ArbArgs {
+    /// Pool of the WETH -> USDC leg, unless the cycle is given with `--hop`.
+    #[arg(long, env = "FIRST_POOL", value_enum, default_value_t = PoolName::V3_500)]
+    first_pool: PoolName,
+
+    /// Pool of the USDC -> WETH leg, unless the cycle is given with `--hop`.
+    #[arg(long, env = "SECOND_POOL", value_enum, default_value_t = PoolName::V3_3000)]
+    second_pool: PoolName,
+
+    /// Hop of the cycle as `<protocol>:<pool>:<token_in>`, repeated in order,
+    /// e.g. `--hop v3-500:v3-500:weth --hop v2:sushiswap:usdc`.
+    ///
+    /// Protocols are `v3-<fee>` and `v2` (0.3% fee), pools and tokens are addresses
+    /// or known names. Each hop ends with the token the next one starts with,
+    /// the cycle starts with WETH since gas is paid in it.
+    #[arg(long = "hop", value_parser = parse_hop)]
+    hops: Vec<HopArg>,
+
+    /// Part of the profit left after gas paid to the builder, in percent.
+    #[arg(long, env = "BUILDER_TIP_PERCENT", default_value_t = 0)]
+    builder_tip_percent: u8,
+
+    /// Tick bitmap words loaded on each side of the current tick for the native cross-check.
+    #[arg(long, default_value_t = 2)]
+    word_radius: i16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[allow(non_camel_case_types)]
+enum PoolName {
+    #[value(name = "v3-500")]
+    V3_500,
+    #[value(name = "v3-3000")]
+    V3_3000,
+    UniswapV2,
+    Sushiswap,
+}
+
+impl PoolName {
+    fn pool(self) -> (Address, Protocol) {
+        match self {
+            PoolName::V3_500 => (V3_POOL_500_ADDR, Protocol::UniV3 { fee: 500 }),
+            PoolName::V3_3000 => (V3_POOL_3000_ADDR, Protocol::UniV3 { fee: 3000 }),
+            PoolName::UniswapV2 => (
+                V2_PAIR_ADDR,
+                Protocol::UniV2 {
+                    fee_numerator: UNI_V2_FEE_NUMERATOR,
+                },
+            ),
+            PoolName::Sushiswap => (
+                SUSHI_PAIR_ADDR,
+                Protocol::UniV2 {
+                    fee_numerator: UNI_V2_FEE_NUMERATOR,
+                },
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct HopArg {
+    protocol: Protocol,
+    pool: Address,
+    token_in: Address,
+}
+
+fn parse_hop(value: &str) -> Result<HopArg, String> {
+    let parts: Vec<_> = value.split(':').map(str::trim).collect();
+    let [protocol, pool, token_in] = parts[..] else {
+        return Err(format!(
+            "expected <protocol>:<pool>:<token_in>, got {value}"
+        ));
+    };
+    Ok(HopArg {
+        protocol: parse_protocol(protocol)?,
+        pool: parse_pool(pool)?,
+        token_in: parse_token(token_in)?,
+    })
+}
+
+fn parse_protocol(value: &str) -> Result<Protocol, String> {
+    if value.eq_ignore_ascii_case("v2") {
+        return Ok(Protocol::UniV2 {
+            fee_numerator: UNI_V2_FEE_NUMERATOR,
+        });
+    }
+    let fee = value
+        .to_lowercase()
+        .strip_prefix("v3-")
+        .ok_or_else(|| format!("unknown protocol {value}, expected v2 or v3-<fee>"))?
+        .parse()
+        .map_err(|e| format!("invalid fee in {value}: {e}"))?;
+    Ok(Protocol::UniV3 { fee })
+}
+
+fn parse_pool(value: &str) -> Result<Address, String> {
+    match PoolName::from_str(value, true) {
+        Ok(name) => Ok(name.pool().0),
+        Err(_) => Address::from_str(value).map_err(|e| format!("unknown pool {value}: {e}")),
+    }
+}
+
+/// Closes the hops into a cycle, every hop ends with the token the next one starts with.
+fn cycle(hops: &[HopArg]) -> anyhow::Result<Path> {
+    ensure!(hops.len() >= 2, "a cycle needs at least two --hop");
+    let next = hops.iter().cycle().skip(1);
+    let hops = hops
+        .iter()
+        .zip(next)
+        .map(|(hop, next)| Hop::new(hop.pool, hop.protocol, hop.token_in, next.token_in))
+        .collect();
+    Path::new(hops)
+}
+
+pub async fn arb(ctx: &Context, args: ArbArgs) -> anyhow::Result<()> {
+    ensure!(
+        args.builder_tip_percent <= 100,
+        "--builder-tip-percent is over 100"
+    );
+
+    let path = if args.hops.is_empty() {
+        let (first_pool, first_protocol) = args.first_pool.pool();
+        let (second_pool, second_protocol) = args.second_pool.pool();
+        Path::new(vec![
+            Hop::new(first_pool, first_protocol, WETH_ADDR, USDC_ADDR),
+            Hop::new(second_pool, second_protocol, USDC_ADDR, WETH_ADDR),
+        ])?
+    } else {
+        cycle(&args.hops)?
+    };
+    ensure!(
+        path.token() == WETH_ADDR,
+        "the cycle starts with {}, not WETH the gas is paid in",
+        token_name(path.token())
+    );
+
+    let provider = ctx.provider()?;
+
+    // Pinning the block (--block) and the chain (CHAIN_ID=...)
+    // lets a restart be served entirely from the on-disk cache.
+    let cache = EvmCache::from_env(&provider).await?;
+    let block_env = fetch_block_env(&provider, ctx.block_id(), &cache).await?;
+    let block_number: u64 = block_env.number.to();
+    let block = BlockId::number(block_number);
+
+    // Gas is paid at the base fee plus the median priority fee of the pinned block.
+    let priority_fee = fetch_priority_fee(&provider, block_number, &cache).await?;
+    let gas_pricing = GasPricing::new(&block_env, priority_fee)
+        .with_builder_tip_percent(args.builder_tip_percent);
+
+    let mut cache_db = init_cache_db(provider.clone(), block, cache);
+
+    init_account(ME, &mut cache_db, provider.clone()).await?;
+    for hop in path.hops() {
+        init_account(hop.pool, &mut cache_db, provider.clone()).await?;
+    }
+
+    // Refresh stale artifacts with `cargo run --bin denegnet -- bytecode refresh`.
+    let registry = BytecodeRegistry::from_env()?;
+    registry
+        .ensure_verified(&provider, block_number, cache_db.db.cache())
+        .await?;
+
+    let tokens: BTreeSet<_> = path.hops().iter().map(|hop| hop.token_in).collect();
+    for &token in &tokens {
+        let implementation = match token {
+            token if token == WETH_ADDR => {
+                init_account_with_bytecode(WETH_ADDR, registry.bytecode("weth")?, &mut cache_db)?;
+                None
+            }
+            // USDC is a proxy, preload its implementation as well.
+            token if token == USDC_ADDR => {
+                init_account_with_bytecode(USDC_ADDR, registry.bytecode("usdc")?, &mut cache_db)?;
+                init_proxy_implementation(USDC_ADDR, &mut cache_db, provider.clone()).await?
+            }
+            token => init_account(token, &mut cache_db, provider.clone()).await?,
+        };
+        if let Some(implementation) = implementation
+            && ctx.format == Format::Text
+        {
+            println!("{} implementation: {implementation}", token_name(token));
+        }
+    }
+
+    init_account_with_bytecode(
+        CUSTOM_QUOTER_ADDR,
+        registry.bytecode("uni_v3_quoter")?,
+        &mut cache_db,
+    )?;
+
+    let mut simulator = Simulator::new(cache_db)
+        .with_caller(ME)
+        .with_block(block_env);
+
+    // The quoter swaps for real, V3 pools must be able to pay out.
+    let mocked_balance = U256::MAX.div(U256::from(2));
+    for hop in path.hops() {
+        if let Protocol::UniV3 { .. } = hop.protocol {
+            for token in [hop.token_in, hop.token_out] {
+                set_erc20_balance(&mut simulator, token, hop.pool, mocked_balance)?;
+            }
+        }
+    }
+
+    // Native state of the pools, used to cross-check the revm quotes.
+    let pools = path.load_pools(simulator.db_mut(), args.word_radius)?;
+
+    let start = Instant::now();
+    let optimum = maximize_profit(
+        |volume| {
+            let result = path.evaluate(Backend::Revm(&mut simulator), volume)?;
+            match path.evaluate(Backend::<AlloyCacheDB>::Native(&pools), volume) {
+                Ok(native) if native.amount_out != result.amount_out => tracing::warn!(
+                    "native quote mismatch for {volume}: {} != {}",
+                    native.amount_out,
+                    result.amount_out
+                ),
+                Ok(_) => {}
+                Err(err) => tracing::warn!("no native quote for {volume}: {err}"),
+            }
+            Ok(result.net_profit(&gas_pricing)?.net_profit)
+        },
+        ONE_ETHER.div(U256::from(100)),
+        ONE_ETHER * U256::from(100),
+        U256::from(TOLERANCE_WEI),
+    )?;
+
+    let result = path.evaluate(Backend::Revm(&mut simulator), optimum.amount_in)?;
+    let net_profit = result.net_profit(&gas_pricing)?;
+
+    match ctx.format {
+        Format::Text => {
+            let route = result.hops.iter().fold(
+                format!("{} {}", optimum.amount_in, token_name(path.token())),
+                |route, hop| {
+                    format!(
+                        "{route} -> {} {}",
+                        hop.amount_out,
+                        token_name(hop.hop.token_out)
+                    )
+                },
+            );
+            // V2 hops are quoted from the reserves, their gas is not simulated.
+            let estimated_gas = match result.estimated_gas() {
+                0 => String::new(),
+                gas => format!(", {gas} of it estimated"),
+            };
+            println!(
+                "{route} ({} gas{estimated_gas}, {} simulations)",
+                result.gas_used, optimum.simulations
+            );
+            println!(
+                "Gross profit: {} WETH, gas: {} WETH, builder tip: {} WETH",
+                result.profit, net_profit.gas_cost, net_profit.builder_tip
+            );
+            if net_profit.net_profit > I256::ZERO {
+                println!("Profit: {} WETH", net_profit.net_profit);
+            } else {
+                println!("No profit after gas: {} WETH", net_profit.net_profit);
+            }
+            println!("-> {:.2?}", start.elapsed());
+        }
+        Format::Json => {
+            let hops: Vec<_> = result
+                .hops
+                .iter()
+                .map(|hop| {
+                    json!({
+                        "pool": hop.hop.pool,
+                        "token_in": hop.hop.token_in,
+                        "token_out": hop.hop.token_out,
+                        "amount_in": hop.amount_in.to_string(),
+                        "amount_out": hop.amount_out.to_string(),
+                        "gas_used": hop.gas_used,
+                        "gas_estimated": hop.gas_estimated,
+                    })
+                })
+                .collect();
+            let arb = json!({
+                "block": block_number,
+                "amount_in": optimum.amount_in.to_string(),
+                "amount_out": result.amount_out.to_string(),
+                "hops": hops,
+                "gas_used": result.gas_used,
+                "estimated_gas": result.estimated_gas(),
+                "simulations": optimum.simulations,
+                "gross_profit": result.profit.to_string(),
+                "gas_cost": net_profit.gas_cost.to_string(),
+                "builder_tip": net_profit.builder_tip.to_string(),
+                "net_profit": net_profit.net_profit.to_string(),
+                "elapsed_ms": start.elapsed().as_millis(),
+            });
+            println!("{}", serde_json::to_string_pretty(&arb)?);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use denegnet::address::DAI_ADDR;
+
+    use super::*;
+
+    #[test]
+    fn parses_hops() {
+        let hop = parse_hop("v3-500:v3-500:weth").unwrap();
+        assert_eq!(
+            hop,
+            HopArg {
+                protocol: Protocol::UniV3 { fee: 500 },
+                pool: V3_POOL_500_ADDR,
+                token_in: WETH_ADDR,
+            }
+        );
+
+        let pool = "0x397FF1542f962076d0BFE58eA045FfA2d347ACa0";
+        let hop = parse_hop(&format!("v2:{pool}:usdc")).unwrap();
+        assert_eq!(hop.pool, SUSHI_PAIR_ADDR);
+        assert_eq!(
+            hop.protocol,
+            Protocol::UniV2 {
+                fee_numerator: UNI_V2_FEE_NUMERATOR
+            }
+        );
+
+        assert!(parse_hop("v3-500:v3-500").is_err());
+        assert!(parse_hop("v4:v3-500:weth").is_err());
+        assert!(parse_hop("v3-fee:v3-500:weth").is_err());
+        assert!(parse_hop("v3-500:pool:weth").is_err());
+    }
+
+    #[test]
+    fn closes_hops_into_a_cycle() {
+        let hops = [
+            parse_hop("v3-500:v3-500:weth").unwrap(),
+            parse_hop("v3-100:0x5777d92f208679db4b9778590fa3cab3ac9e2168:usdc").unwrap(),
+            parse_hop("v2:0xa478c2975ab1ea89e8196811f51a7b7ade33eb11:dai").unwrap(),
+        ];
+        let path = cycle(&hops).unwrap();
+
+        let tokens: Vec<_> = path
+            .hops()
+            .iter()
+            .map(|hop| (hop.token_in, hop.token_out))
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                (WETH_ADDR, USDC_ADDR),
+                (USDC_ADDR, DAI_ADDR),
+                (DAI_ADDR, WETH_ADDR)
+            ]
+        );
+
+        assert!(cycle(&hops[..1]).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use std::path::PathBuf;
+
+use alloy::eips::BlockId;
+use clap::{Args, Subcommand};
+use denegnet::{
+    bytecode::{
+        BYTECODE_DIR, BytecodeRegistry, FOUNDRY_OUT, FOUNDRY_ROOT, forge_build, refresh,
+        refresh_from_foundry_out,
+    },
+    cache::EvmCache,
+    revm::fetch_block_env,
+};
+use serde_json::json;
+
+use crate::{Context, Format};
+
+#[derive(Args)]
+pub struct BytecodeArgs {
+    /// Artifacts location.
+    #[arg(long, env = "BYTECODE_DIR", default_value = BYTECODE_DIR, global = true)]
+    dir: PathBuf,
+
+    /// Runs `forge build` in `contracts/` and also checks (or refreshes)
+    /// the artifacts built from source.
+    #[arg(long, global = true)]
+    build: bool,
+
+    #[command(subcommand)]
+    command: BytecodeCommand,
+}
+
+#[derive(Subcommand)]
+enum BytecodeCommand {
+    /// Compares the artifacts with the on-chain code hash.
+    Verify,
+    /// Fetches the on-chain code of the artifacts and rewrites their hex files.
+    Refresh,
+}
+
+pub async fn bytecode(ctx: &Context, args: BytecodeArgs) -> anyhow::Result<()> {
+    let provider = ctx.provider()?;
+    let cache = EvmCache::from_env(&provider).await?;
+    let block: u64 = fetch_block_env(&provider, ctx.block_id(), &cache)
+        .await?
+        .number
+        .to();
+
+    if args.build {
+        forge_build(FOUNDRY_ROOT)?;
+    }
+
+    match args.command {
+        BytecodeCommand::Verify => {
+            let registry = BytecodeRegistry::from_dir(&args.dir)?;
+            let mismatches = registry.verify(&provider, block, &cache).await?;
+            let source_mismatches = match args.build {
+                true => registry.verify_foundry_out(FOUNDRY_OUT)?,
+                false => Vec::new(),
+            };
+            match ctx.format {
+                Format::Text => {
+                    for mismatch in &mismatches {
+                        println!(
+                            "{} ({}): {} on-chain, {} local",
+                            mismatch.name, mismatch.address, mismatch.expected, mismatch.actual
+                        );
+                    }
+                    for mismatch in &source_mismatches {
+                        println!(
+                            "{}: {} built from source, {} local",
+                            mismatch.name, mismatch.built, mismatch.local
+                        );
+                    }
+                }
+                Format::Json => {
+                    let on_chain: Vec<_> = mismatches
+                        .iter()
+                        .map(|mismatch| {
+                            json!({
+                                "name": mismatch.name,
+                                "address": mismatch.address,
+                                "expected": mismatch.expected,
+                                "actual": mismatch.actual,
+                            })
+                        })
+                        .collect();
+                    let source: Vec<_> = source_mismatches
+                        .iter()
+                        .map(|mismatch| {
+                            json!({
+                                "name": mismatch.name,
+                                "built": mismatch.built,
+                                "local": mismatch.local,
+                            })
+                        })
+                        .collect();
+                    let report = json!({"block": block, "on_chain": on_chain, "source": source});
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                }
+            }
+            let stale = mismatches.len() + source_mismatches.len();
+            if stale > 0 {
+                anyhow::bail!("{stale} stale artifacts at block {block}");
+            }
+        }
+        BytecodeCommand::Refresh => {
+            let mut written = refresh(&args.dir, &provider, BlockId::number(block)).await?;
+            if args.build {
+                written.extend(refresh_from_foundry_out(&args.dir, FOUNDRY_OUT)?);
+            }
+            for path in written {
+                println!("Wrote {}", path.display());
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+
+use alloy::providers::Provider;
+use anyhow::bail;
+use clap::{Args, Subcommand};
+use denegnet::cache::{CACHE_DIR, EvmCache, key_block};
+use serde_json::json;
+
+use crate::{Context, Format};
+
+#[derive(Args)]
+pub struct CacheArgs {
+    /// Cache location.
+    #[arg(long, env = "EVM_CACHE_DIR", default_value = CACHE_DIR, global = true)]
+    dir: PathBuf,
+
+    /// Chain the entries are namespaced by, `eth_chainId` of `--rpc-url` if not set.
+    #[arg(long, env = "CHAIN_ID", global = true)]
+    chain_id: Option<u64>,
+
+    #[command(subcommand)]
+    command: CacheCommand,
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Lists cached entries.
+    List,
+    /// Re-hashes cached bytecode against its code hash,
+    /// `bytecode-*` entries are also compared with the chain if `--rpc-url` is set.
+    Verify,
+    /// Removes cached entries.
+    Purge {
+        /// Only removes entries stored for blocks older than this one.
+        #[arg(long)]
+        before: Option<u64>,
+    },
+}
+
+pub async fn cache(ctx: &Context, args: CacheArgs) -> anyhow::Result<()> {
+    // Same chain as `EvmCache::from_env` picks for the other commands.
+    let chain_id = match (args.chain_id, &ctx.rpc_url) {
+        (Some(chain_id), _) => chain_id,
+        (None, Some(_)) => ctx.provider()?.get_chain_id().await?,
+        (None, None) => bail!("--chain-id (or CHAIN_ID) is required without --rpc-url"),
+    };
+    let cache = EvmCache::new(args.dir, chain_id);
+
+    match args.command {
+        CacheCommand::List => {
+            let mut entries = Vec::new();
+            for entry in cache.entries() {
+                let entry = entry?;
+                entries.push((entry.key.clone(), key_block(&entry.key), entry.size));
+            }
+            match ctx.format {
+                Format::Text => {
+                    for (key, block, size) in entries {
+                        let block = block.map_or("-".to_string(), |b| b.to_string());
+                        println!("{key}\t{block}\t{size}");
+                    }
+                }
+                Format::Json => {
+                    let entries: Vec<_> = entries
+                        .into_iter()
+                        .map(|(key, block, size)| json!({"key": key, "block": block, "size": size}))
+                        .collect();
+                    println!("{}", serde_json::to_string_pretty(&entries)?);
+                }
+            }
+        }
+        CacheCommand::Verify => {
+            let provider = ctx.rpc_url.is_some().then(|| ctx.provider()).transpose()?;
+            let issues = cache.verify(provider.as_ref()).await?;
+            match ctx.format {
+                Format::Text => {
+                    for issue in &issues {
+                        println!("{}: {}", issue.key, issue.reason);
+                    }
+                }
+                Format::Json => {
+                    let issues: Vec<_> = issues
+                        .iter()
+                        .map(|issue| json!({"key": issue.key, "reason": issue.reason}))
+                        .collect();
+                    println!("{}", serde_json::to_string_pretty(&issues)?);
+                }
+            }
+            if !issues.is_empty() {
+                bail!("{} broken entries", issues.len());
+            }
+        }
+        CacheCommand::Purge { before } => {
+            let removed = match before {
+                Some(block) => cache.prune(block)?,
+                None => cache.purge()?,
+            };
+            match ctx.format {
+                Format::Text => println!("Removed {removed} entries"),
+                Format::Json => println!("{}", json!({"removed": removed})),
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,219 @@
+use std::{path::PathBuf, str::FromStr, sync::Arc};
+
+use alloy::{
+    eips::BlockId,
+    primitives::Address,
+    providers::{Provider, ProviderBuilder},
+    transports::http::reqwest::Url,
+};
+use anyhow::anyhow;
+use clap::{Parser, Subcommand, ValueEnum};
+use denegnet::{
+    address::{DAI_ADDR, USDC_ADDR, WETH_ADDR},
+    revm::RevmProvider,
+    rpc::{RpcStats, provider_with_stats},
+    setup_tracing,
+    uni_v3::V3Path,
+};
+
+mod arb;
+mod bytecode;
+mod cache;
+mod quote;
+mod quoter;
+mod verify;
+
+#[derive(Parser)]
+#[command(about = "Uniswap quotes and arbitrage over eth_call, Anvil, revm and native math")]
+struct Args {
+    #[arg(long, env = "ETH_RPC_URL", global = true)]
+    rpc_url: Option<Url>,
+
+    /// Block to read the state at, the latest one by default.
+    #[arg(long, env = "BLOCK_NUMBER", global = true)]
+    block: Option<u64>,
+
+    #[arg(long, value_enum, default_value_t = Format::Text, global = true)]
+    format: Format,
+
+    /// Prints the RPC requests by method to stderr once done.
+    #[arg(long, global = true)]
+    rpc_stats: bool,
+
+    /// Writes every RPC request and response to this JSON file once done.
+    #[arg(long, global = true)]
+    rpc_record: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Quotes a single volume.
+    Quote(quote::QuoteArgs),
+    /// Quotes a range of volumes.
+    Sweep(quote::SweepArgs),
+    /// Finds the most profitable volume of a cycle through two or more pools.
+    Arb(arb::ArbArgs),
+    /// Measures quote latency and RPC requests of several backends.
+    Bench(quote::BenchArgs),
+    /// Manages the on-disk EVM cache.
+    Cache(cache::CacheArgs),
+    /// Compares the quotes of revm (or native) with eth_call (or Anvil) at the same block.
+    Verify(verify::VerifyArgs),
+    /// Checks and refreshes the bytecode artifacts loaded instead of fetched.
+    Bytecode(bytecode::BytecodeArgs),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+/// Settings shared by all the commands.
+#[derive(Clone)]
+pub struct Context {
+    rpc_url: Option<Url>,
+    block: Option<u64>,
+    pub format: Format,
+    rpc_stats: Option<RpcStats>,
+}
+
+impl Context {
+    pub fn rpc_url(&self) -> anyhow::Result<Url> {
+        self.rpc_url
+            .clone()
+            .ok_or_else(|| anyhow!("--rpc-url or ETH_RPC_URL is required"))
+    }
+
+    pub fn provider(&self) -> anyhow::Result<RevmProvider> {
+        match &self.rpc_stats {
+            Some(stats) => Ok(provider_with_stats(self.rpc_url()?, stats.clone())),
+            None => Ok(Arc::new(
+                ProviderBuilder::new().connect_http(self.rpc_url()?),
+            )),
+        }
+    }
+
+    /// The pinned block number, the latest one if not set.
+    pub async fn block_number(&self, provider: &RevmProvider) -> anyhow::Result<u64> {
+        match self.block {
+            Some(number) => Ok(number),
+            None => Ok(provider.get_block_number().await?),
+        }
+    }
+
+    /// Pins the block, so several backends read the same state.
+    pub fn with_block(&self, block: u64) -> Self {
+        Self {
+            block: Some(block),
+            ..self.clone()
+        }
+    }
+
+    /// Collects the requests of the providers created from now on.
+    pub fn with_rpc_stats(&self, stats: RpcStats) -> Self {
+        Self {
+            rpc_stats: Some(stats),
+            ..self.clone()
+        }
+    }
+
+    pub fn block_id(&self) -> BlockId {
+        self.block.map_or(BlockId::latest(), BlockId::number)
+    }
+}
+
+/// Parses a known token symbol (weth, usdc, dai) or an address.
+pub fn parse_token(value: &str) -> Result<Address, String> {
+    match value.to_lowercase().as_str() {
+        "weth" => Ok(WETH_ADDR),
+        "usdc" => Ok(USDC_ADDR),
+        "dai" => Ok(DAI_ADDR),
+        _ => Address::from_str(value).map_err(|e| format!("unknown token {value}: {e}")),
+    }
+}
+
+/// Parses a route of tokens and fee tiers, e.g. `weth,500,usdc,100,dai`.
+pub fn parse_path(value: &str) -> Result<V3Path, String> {
+    let parts: Vec<_> = value.split(',').map(str::trim).collect();
+    let tokens = parts
+        .iter()
+        .step_by(2)
+        .map(|token| parse_token(token))
+        .collect::<Result<_, _>>()?;
+    let fees = parts
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .map(|fee| fee.parse().map_err(|e| format!("invalid fee {fee}: {e}")))
+        .collect::<Result<_, _>>()?;
+    V3Path::new(tokens, fees).map_err(|e| e.to_string())
+}
+
+pub fn token_name(token: Address) -> String {
+    match token {
+        token if token == WETH_ADDR => "WETH".to_string(),
+        token if token == USDC_ADDR => "USDC".to_string(),
+        token if token == DAI_ADDR => "DAI".to_string(),
+        token => token.to_string(),
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    setup_tracing();
+
+    let args = Args::parse();
+    let rpc_stats = match (args.rpc_stats, &args.rpc_record) {
+        (_, Some(_)) => Some(RpcStats::recording()),
+        (true, None) => Some(RpcStats::new()),
+        (false, None) => None,
+    };
+    let ctx = Context {
+        rpc_url: args.rpc_url,
+        block: args.block,
+        format: args.format,
+        rpc_stats: rpc_stats.clone(),
+    };
+
+    let result = match args.command {
+        Command::Quote(args) => quote::quote(&ctx, args).await,
+        Command::Sweep(args) => quote::sweep(&ctx, args).await,
+        Command::Arb(args) => arb::arb(&ctx, args).await,
+        Command::Bench(args) => quote::bench(&ctx, args).await,
+        Command::Cache(args) => cache::cache(&ctx, args).await,
+        Command::Verify(args) => verify::verify(&ctx, args).await,
+        Command::Bytecode(args) => bytecode::bytecode(&ctx, args).await,
+    };
+
+    // Also reported for failed commands, the requests may be the reason.
+    if let Some(stats) = rpc_stats {
+        let summary = stats.summary();
+        if args.rpc_stats {
+            eprintln!("{summary}");
+        }
+        if let Some(path) = args.rpc_record {
+            std::fs::write(path, serde_json::to_string_pretty(&summary)?)?;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_paths() {
+        let path = parse_path("weth,500,usdc, 100 ,dai").unwrap();
+        assert_eq!(path.tokens(), [WETH_ADDR, USDC_ADDR, DAI_ADDR]);
+        assert_eq!(path.fees(), [500, 100]);
+
+        assert!(parse_path("weth").is_err());
+        assert!(parse_path("weth,500").is_err());
+        assert!(parse_path("weth,fee,usdc").is_err());
+    }
+}
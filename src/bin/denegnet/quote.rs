@@ -0,0 +1,446 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use alloy::primitives::U256;
+use anyhow::ensure;
+use clap::Args;
+use denegnet::{
+    abi::{
+        PathQuoteResult, QuoteResult, decode_quote_exact_input_response,
+        decode_quote_exact_output_response, decode_quote_exact_output_single_response,
+        quote_exact_input_calldata, quote_exact_output_calldata,
+        quote_exact_output_single_calldata,
+    },
+    bench::{BenchRun, bench_quotes},
+    helpers::volumes,
+    rpc::{RpcStats, RpcSummary},
+    uni_v3::V3Path,
+};
+use serde_json::{Value, json};
+
+use crate::{
+    Context, Format, parse_path,
+    quoter::{Backend, PoolArgs, PoolQuoter},
+    token_name,
+};
+
+#[derive(Args)]
+pub struct QuoteArgs {
+    #[arg(long, value_enum, default_value_t = Backend::EthCall)]
+    backend: Backend,
+
+    #[command(flatten)]
+    pool: PoolArgs,
+
+    /// Amount in (amount out with `--exact-output`), in the smallest unit of the token.
+    #[arg(long, default_value = "100000000000000000")]
+    volume: U256,
+
+    /// Quotes the amount in needed to receive `--volume`.
+    #[arg(long)]
+    exact_output: bool,
+
+    /// Multi-hop route, e.g. `weth,500,usdc,100,dai`, replaces the pool tokens and fee.
+    #[arg(long, value_parser = parse_path, conflicts_with_all = ["token_in", "token_out", "fee", "pool"])]
+    path: Option<V3Path>,
+}
+
+/// `count` volumes evenly spaced in `(from, to]`, largest first.
+#[derive(Args)]
+pub struct VolumeArgs {
+    /// Amount in, in the smallest unit of the token.
+    #[arg(long, default_value = "0")]
+    from: U256,
+
+    #[arg(long, default_value = "100000000000000000")]
+    to: U256,
+
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    count: u64,
+}
+
+impl VolumeArgs {
+    pub fn volumes(&self) -> anyhow::Result<Vec<U256>> {
+        volumes(self.from, self.to, self.count as usize)
+    }
+}
+
+#[derive(Args)]
+pub struct SweepArgs {
+    #[arg(long, value_enum, default_value_t = Backend::Revm)]
+    backend: Backend,
+
+    #[command(flatten)]
+    pool: PoolArgs,
+
+    #[command(flatten)]
+    volumes: VolumeArgs,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    /// Anvil is left out by default, it needs `anvil` in `PATH`.
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [Backend::EthCall, Backend::Revm, Backend::RevmCached, Backend::Native]
+    )]
+    backends: Vec<Backend>,
+
+    /// Unmeasured sweeps run after the first quote.
+    #[arg(long, default_value_t = 1)]
+    warmup: usize,
+
+    /// Measured sweeps, every quote is timed on its own.
+    #[arg(long, default_value_t = 5)]
+    runs: usize,
+
+    #[command(flatten)]
+    pool: PoolArgs,
+
+    #[command(flatten)]
+    volumes: VolumeArgs,
+}
+
+/// Measurements of one backend.
+struct BenchRow {
+    backend: Backend,
+    setup: Duration,
+    setup_rpc: RpcSummary,
+    first_quote: Duration,
+    first_quote_rpc: RpcSummary,
+    run: BenchRun,
+}
+
+pub async fn quote(ctx: &Context, args: QuoteArgs) -> anyhow::Result<()> {
+    if args.exact_output || args.path.is_some() {
+        return quote_route(ctx, args).await;
+    }
+
+    let start = Instant::now();
+    let quoter = PoolQuoter::new(ctx, args.backend, args.pool).await?;
+    let quote = quoter.quote(args.volume).await?;
+
+    match ctx.format {
+        Format::Text => {
+            println!("{}", quote_line(&quoter, args.volume, &quote));
+            println!(
+                "sqrtPriceX96 after: {}, ticks crossed: {}, gas estimate: {}",
+                quote.sqrt_price_x96_after, quote.ticks_crossed, quote.gas_estimate
+            );
+            println!("-> {:.2?}", start.elapsed());
+        }
+        Format::Json => {
+            let mut quote = quote_json(args.volume, &quote);
+            quote["elapsed_ms"] = json!(start.elapsed().as_millis());
+            println!("{}", serde_json::to_string_pretty(&quote)?);
+        }
+    }
+    Ok(())
+}
+
+/// Exact-output or multi-hop quote, through the Quoter V2 functions of the route.
+async fn quote_route(ctx: &Context, args: QuoteArgs) -> anyhow::Result<()> {
+    ensure!(
+        args.pool.slippage_bps.is_none(),
+        "--slippage-bps only applies to exact-input single-pool quotes"
+    );
+
+    let start = Instant::now();
+    let mut pool = args.pool.clone();
+    if let Some(path) = &args.path {
+        // The first pool is preloaded by `revm-cached`, the others are fetched on demand.
+        let (token_in, fee, token_out) = path.pools().next().expect("path has a pool");
+        pool.token_in = token_in;
+        pool.token_out = token_out;
+        pool.fee = fee;
+    }
+    let quoter = PoolQuoter::new(ctx, args.backend, pool).await?;
+
+    let quote = match (&args.path, args.exact_output) {
+        (None, _) => {
+            let calldata = quote_exact_output_single_calldata(
+                args.pool.token_in,
+                args.pool.token_out,
+                args.volume,
+                args.pool.fee,
+            );
+            let quote =
+                decode_quote_exact_output_single_response(quoter.backend.call(calldata).await?)?;
+            PathQuoteResult {
+                amount: quote.amount_in,
+                sqrt_price_x96_after: vec![quote.sqrt_price_x96_after],
+                ticks_crossed: vec![quote.ticks_crossed],
+                gas_estimate: quote.gas_estimate,
+            }
+        }
+        (Some(path), false) => {
+            let calldata = quote_exact_input_calldata(path, args.volume);
+            decode_quote_exact_input_response(quoter.backend.call(calldata).await?)?
+        }
+        (Some(path), true) => {
+            let calldata = quote_exact_output_calldata(path, args.volume);
+            decode_quote_exact_output_response(quoter.backend.call(calldata).await?)?
+        }
+    };
+
+    let tokens = match &args.path {
+        Some(path) => path.tokens().to_vec(),
+        None => vec![args.pool.token_in, args.pool.token_out],
+    };
+    let (amount_in, amount_out) = match args.exact_output {
+        true => (quote.amount, args.volume),
+        false => (args.volume, quote.amount),
+    };
+
+    match ctx.format {
+        Format::Text => {
+            let route: Vec<_> = tokens.iter().map(|token| token_name(*token)).collect();
+            println!("{amount_in} {} {amount_out}", route.join(" -> "));
+            println!(
+                "sqrtPriceX96 after: {:?}, ticks crossed: {:?}, gas estimate: {}",
+                quote.sqrt_price_x96_after, quote.ticks_crossed, quote.gas_estimate
+            );
+            println!("-> {:.2?}", start.elapsed());
+        }
+        Format::Json => {
+            let quote = json!({
+                "tokens": tokens,
+                "exact_output": args.exact_output,
+                "amount_in": amount_in.to_string(),
+                "amount_out": amount_out.to_string(),
+                "sqrt_price_x96_after": quote
+                    .sqrt_price_x96_after
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+                "ticks_crossed": quote.ticks_crossed,
+                "gas_estimate": quote.gas_estimate.to_string(),
+                "elapsed_ms": start.elapsed().as_millis(),
+            });
+            println!("{}", serde_json::to_string_pretty(&quote)?);
+        }
+    }
+    Ok(())
+}
+
+pub async fn sweep(ctx: &Context, args: SweepArgs) -> anyhow::Result<()> {
+    let volumes = args.volumes.volumes()?;
+    let quoter = PoolQuoter::new(ctx, args.backend, args.pool).await?;
+
+    // The first quote fetches the state, the others mostly read it from memory.
+    let start = Instant::now();
+    let first_quote = quoter.quote(volumes[0]).await?;
+    let first_elapsed = start.elapsed();
+    if ctx.format == Format::Text {
+        println!("-> {first_elapsed:.2?}");
+        println!("{}", quote_line(&quoter, volumes[0], &first_quote));
+    }
+
+    let sweep_start = Instant::now();
+    let quotes = quoter.quote_batch(&volumes).await?;
+    let sweep_elapsed = sweep_start.elapsed();
+
+    match ctx.format {
+        Format::Text => {
+            for (volume, quote) in volumes.iter().zip(&quotes) {
+                println!("{}", quote_line(&quoter, *volume, quote));
+            }
+            println!("-> {sweep_elapsed:.2?}");
+        }
+        Format::Json => {
+            let sweep = json!({
+                "backend": format!("{:?}", args.backend),
+                "first_quote_ms": first_elapsed.as_millis(),
+                "sweep_ms": sweep_elapsed.as_millis(),
+                "quotes": volumes
+                    .iter()
+                    .zip(&quotes)
+                    .map(|(volume, quote)| quote_json(*volume, quote))
+                    .collect::<Vec<_>>(),
+            });
+            println!("{}", serde_json::to_string_pretty(&sweep)?);
+        }
+    }
+    Ok(())
+}
+
+pub async fn bench(ctx: &Context, args: BenchArgs) -> anyhow::Result<()> {
+    let volumes = args.volumes.volumes()?;
+
+    // All the backends quote the same state.
+    let block = ctx.block_number(&ctx.provider()?).await?;
+    // Reset between phases, the requests also go to `--rpc-stats` and `--rpc-record`.
+    let stats = ctx
+        .rpc_stats
+        .as_ref()
+        .map_or_else(RpcStats::new, RpcStats::scoped);
+    let ctx = ctx.with_block(block).with_rpc_stats(stats.clone());
+
+    let mut rows = Vec::new();
+    for backend in args.backends {
+        stats.reset();
+        let start = Instant::now();
+        let quoter = PoolQuoter::new(&ctx, backend, args.pool.clone()).await?;
+        let setup = start.elapsed();
+        let setup_rpc = stats.reset();
+
+        let start = Instant::now();
+        quoter.quote(volumes[0]).await?;
+        let first_quote = start.elapsed();
+        let first_quote_rpc = stats.reset();
+
+        let requests: Vec<_> = volumes
+            .iter()
+            .map(|volume| quoter.request(*volume))
+            .collect();
+        let run = bench_quotes(
+            &quoter.backend,
+            &requests,
+            args.warmup,
+            args.runs,
+            Some(&stats),
+        )
+        .await?;
+
+        rows.push(BenchRow {
+            backend,
+            setup,
+            setup_rpc,
+            first_quote,
+            first_quote_rpc,
+            run,
+        });
+    }
+
+    match ctx.format {
+        Format::Text => {
+            println!(
+                "block {block}, {} quotes x {} runs",
+                volumes.len(),
+                args.runs
+            );
+            println!(
+                "{:<12} {:>10} {:>12} {:>10} {:>10} {:>10} {:>10} {:>6} {:>6} {:>6}",
+                "backend",
+                "setup",
+                "first quote",
+                "p50",
+                "p90",
+                "p99",
+                "max",
+                "rpc0",
+                "rpc1",
+                "rpcN"
+            );
+            for row in &rows {
+                let latency = row.run.latency;
+                println!(
+                    "{:<12} {:>10} {:>12} {:>10} {:>10} {:>10} {:>10} {:>6} {:>6} {:>6}",
+                    format!("{:?}", row.backend),
+                    format_duration(row.setup),
+                    format_duration(row.first_quote),
+                    format_duration(latency.p50),
+                    format_duration(latency.p90),
+                    format_duration(latency.p99),
+                    format_duration(latency.max),
+                    row.setup_rpc.total(),
+                    row.first_quote_rpc.total(),
+                    row.run.rpc.total()
+                );
+            }
+            println!("rpc0: setup, rpc1: first quote, rpcN: measured runs");
+            for row in &rows {
+                let mut methods = BTreeMap::<&str, u64>::new();
+                for summary in [&row.setup_rpc, &row.first_quote_rpc, &row.run.rpc] {
+                    for (method, stats) in &summary.methods {
+                        *methods.entry(method).or_default() += stats.calls;
+                    }
+                }
+                let methods: Vec<_> = methods
+                    .iter()
+                    .map(|(method, count)| format!("{method} {count}"))
+                    .collect();
+                println!("{:?}: {}", row.backend, methods.join(", "));
+            }
+        }
+        Format::Json => {
+            let rows: Vec<_> = rows
+                .iter()
+                .map(|row| {
+                    let latency = row.run.latency;
+                    json!({
+                        "backend": format!("{:?}", row.backend),
+                        "setup_us": row.setup.as_micros(),
+                        "first_quote_us": row.first_quote.as_micros(),
+                        "latency_us": {
+                            "samples": latency.samples,
+                            "min": latency.min.as_micros(),
+                            "mean": latency.mean.as_micros(),
+                            "p50": latency.p50.as_micros(),
+                            "p90": latency.p90.as_micros(),
+                            "p99": latency.p99.as_micros(),
+                            "max": latency.max.as_micros(),
+                        },
+                        "rpc": {
+                            "setup": row.setup_rpc.counts(),
+                            "first_quote": row.first_quote_rpc.counts(),
+                            "runs": row.run.rpc.counts(),
+                        },
+                    })
+                })
+                .collect();
+            let bench = json!({
+                "block": block,
+                "quotes": volumes.len(),
+                "warmup": args.warmup,
+                "runs": args.runs,
+                "backends": rows,
+            });
+            println!("{}", serde_json::to_string_pretty(&bench)?);
+        }
+    }
+
+    // The cached backend must not fetch bytecode once warmed up.
+    for row in &rows {
+        let get_code = row.run.rpc.count("eth_getCode");
+        ensure!(
+            row.backend != Backend::RevmCached || get_code == 0,
+            "{:?} sent {get_code} eth_getCode requests after the warmup",
+            row.backend
+        );
+    }
+    Ok(())
+}
+
+fn quote_line(quoter: &PoolQuoter, volume: U256, quote: &QuoteResult) -> String {
+    let partial_fill = if quote.partial_fill {
+        " (partial fill)"
+    } else {
+        ""
+    };
+    format!(
+        "{volume} {} -> {} {}{partial_fill}",
+        token_name(quoter.pool.token_in),
+        token_name(quoter.pool.token_out),
+        quote.amount_out
+    )
+}
+
+fn quote_json(volume: U256, quote: &QuoteResult) -> Value {
+    json!({
+        "volume": volume.to_string(),
+        "amount_out": quote.amount_out.to_string(),
+        "sqrt_price_x96_after": quote.sqrt_price_x96_after.to_string(),
+        "ticks_crossed": quote.ticks_crossed,
+        "gas_estimate": quote.gas_estimate.to_string(),
+        "partial_fill": quote.partial_fill,
+    })
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2?}", duration)
+}
@@ -0,0 +1,198 @@
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, Bytes, U160, U256},
+};
+use anyhow::bail;
+use clap::{Args, ValueEnum};
+use denegnet::{
+    abi::QuoteResult,
+    cache::EvmCache,
+    quote::{
+        AnvilBackend, EthCallBackend, NativeBackend, PoolKey, QuoteBackend, QuoteRequest,
+        RevmBackend,
+    },
+    revm::init_cache_db,
+    uni_v3::{load_slot0, slippage_sqrt_price_limit},
+};
+
+use crate::{Context, parse_token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    /// `eth_call` to the Quoter V2 on the node.
+    EthCall,
+    /// `eth_call` to the Quoter V2 on a local Anvil fork of the node.
+    Anvil,
+    /// Quoter V2 in revm, all the state is fetched on demand.
+    Revm,
+    /// Quoter V2 in revm, the accounts are preloaded and the bytecode is cached.
+    RevmCached,
+    /// Native Uniswap V3 math over the pool storage.
+    Native,
+}
+
+/// Single-pool exact-input swap to quote.
+#[derive(Args, Debug, Clone)]
+pub struct PoolArgs {
+    #[arg(long, value_parser = parse_token, default_value = "weth")]
+    pub token_in: Address,
+
+    #[arg(long, value_parser = parse_token, default_value = "usdc")]
+    pub token_out: Address,
+
+    /// Fee tier in hundredths of a bip.
+    #[arg(long, default_value_t = 3000)]
+    pub fee: u32,
+
+    /// Pool address, looked up in the factory if not set.
+    #[arg(long)]
+    pub pool: Option<Address>,
+
+    /// Quoter V2 address, the mainnet one by default.
+    #[arg(long)]
+    pub quoter: Option<Address>,
+
+    /// Stops the swap once the price moves this far from the current one.
+    #[arg(long, env = "SLIPPAGE_BPS")]
+    pub slippage_bps: Option<u32>,
+
+    /// Tick bitmap words loaded on each side of the current tick by the native backend.
+    #[arg(long, default_value_t = 2)]
+    pub word_radius: i16,
+}
+
+/// Any of the backends, picked with `--backend`.
+pub enum AnyBackend {
+    EthCall(EthCallBackend),
+    Anvil(AnvilBackend),
+    Revm(Box<RevmBackend>),
+    Native(NativeBackend),
+}
+
+impl QuoteBackend for AnyBackend {
+    async fn quote(&self, request: QuoteRequest) -> anyhow::Result<QuoteResult> {
+        match self {
+            AnyBackend::EthCall(backend) => backend.quote(request).await,
+            AnyBackend::Anvil(backend) => backend.quote(request).await,
+            AnyBackend::Revm(backend) => backend.quote(request).await,
+            AnyBackend::Native(backend) => backend.quote(request).await,
+        }
+    }
+
+    async fn quote_batch(&self, requests: Vec<QuoteRequest>) -> anyhow::Result<Vec<QuoteResult>> {
+        match self {
+            AnyBackend::EthCall(backend) => backend.quote_batch(requests).await,
+            AnyBackend::Anvil(backend) => backend.quote_batch(requests).await,
+            AnyBackend::Revm(backend) => backend.quote_batch(requests).await,
+            AnyBackend::Native(backend) => backend.quote_batch(requests).await,
+        }
+    }
+}
+
+impl AnyBackend {
+    /// Calls the quoter with any of its functions, the native backend only
+    /// quotes exact-input single-pool swaps.
+    pub async fn call(&self, calldata: Bytes) -> anyhow::Result<Bytes> {
+        match self {
+            AnyBackend::EthCall(backend) => backend.call(calldata).await,
+            AnyBackend::Anvil(backend) => backend.call(calldata).await,
+            AnyBackend::Revm(backend) => backend.call(calldata),
+            AnyBackend::Native(_) => {
+                bail!("the native backend only quotes exact-input single-pool swaps")
+            }
+        }
+    }
+}
+
+/// Backend with the swap it quotes.
+pub struct PoolQuoter {
+    pub backend: AnyBackend,
+    pub pool: PoolArgs,
+    sqrt_price_limit_x96: Option<U160>,
+}
+
+impl PoolQuoter {
+    pub async fn new(ctx: &Context, backend: Backend, pool: PoolArgs) -> anyhow::Result<Self> {
+        let provider = ctx.provider()?;
+        let block = ctx.block_number(&provider).await?;
+
+        let pool_key = match pool.pool {
+            Some(address) => PoolKey::new(address, pool.token_in, pool.token_out, pool.fee),
+            None => {
+                PoolKey::find(&provider, block, pool.token_in, pool.token_out, pool.fee).await?
+            }
+        };
+        let sqrt_price_limit_x96 = match pool.slippage_bps {
+            Some(bps) => {
+                let cache = EvmCache::from_env(&provider).await?;
+                let mut db = init_cache_db(provider.clone(), BlockId::number(block), cache);
+                let (sqrt_price_x96, _) = load_slot0(&mut db, pool_key.address)?;
+                let zero_for_one = pool.token_in < pool.token_out;
+                let limit = slippage_sqrt_price_limit(sqrt_price_x96, zero_for_one, bps);
+                Some(limit.to())
+            }
+            None => None,
+        };
+
+        let backend = match backend {
+            Backend::EthCall => AnyBackend::EthCall(EthCallBackend::new(provider, block)),
+            Backend::Anvil => AnyBackend::Anvil(AnvilBackend::spawn(ctx.rpc_url()?, block).await?),
+            Backend::Revm => {
+                let cache = EvmCache::from_env(&provider).await?;
+                AnyBackend::Revm(Box::new(RevmBackend::fetch(provider, block, cache).await?))
+            }
+            Backend::RevmCached => {
+                let cache = EvmCache::from_env(&provider).await?;
+                let backend = RevmBackend::prewarmed(provider, block, cache, &[pool_key]).await?;
+                AnyBackend::Revm(Box::new(backend))
+            }
+            Backend::Native => {
+                let cache = EvmCache::from_env(&provider).await?;
+                let mut simulator = RevmBackend::fetch(provider, block, cache)
+                    .await?
+                    .into_simulator();
+                let backend =
+                    NativeBackend::load(simulator.db_mut(), &[pool_key], pool.word_radius)?;
+                AnyBackend::Native(backend)
+            }
+        };
+
+        let backend = match (backend, pool.quoter) {
+            (AnyBackend::EthCall(backend), Some(quoter)) => {
+                AnyBackend::EthCall(backend.with_quoter(quoter))
+            }
+            (AnyBackend::Anvil(backend), Some(quoter)) => {
+                AnyBackend::Anvil(backend.with_quoter(quoter))
+            }
+            (AnyBackend::Revm(backend), Some(quoter)) => {
+                AnyBackend::Revm(Box::new(backend.with_quoter(quoter)))
+            }
+            (backend, _) => backend,
+        };
+
+        Ok(Self {
+            backend,
+            pool,
+            sqrt_price_limit_x96,
+        })
+    }
+
+    pub fn request(&self, amount_in: U256) -> QuoteRequest {
+        QuoteRequest::new(
+            self.pool.token_in,
+            self.pool.token_out,
+            self.pool.fee,
+            amount_in,
+        )
+        .with_sqrt_price_limit_x96(self.sqrt_price_limit_x96)
+    }
+
+    pub async fn quote(&self, amount_in: U256) -> anyhow::Result<QuoteResult> {
+        self.backend.quote(self.request(amount_in)).await
+    }
+
+    pub async fn quote_batch(&self, volumes: &[U256]) -> anyhow::Result<Vec<QuoteResult>> {
+        let requests = volumes.iter().map(|volume| self.request(*volume)).collect();
+        self.backend.quote_batch(requests).await
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::bail;
+use clap::Args;
+use denegnet::verify::{Mismatch, verify as verify_backends};
+use serde_json::json;
+
+use crate::{
+    Context, Format,
+    quote::VolumeArgs,
+    quoter::{Backend, PoolArgs, PoolQuoter},
+};
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// Backend trusted to be right.
+    #[arg(long, value_enum, default_value_t = Backend::EthCall)]
+    reference: Backend,
+
+    /// Backend checked against the reference.
+    #[arg(long, value_enum, default_value_t = Backend::RevmCached)]
+    candidate: Backend,
+
+    /// Largest accepted gas estimate difference, ignored for the native backend.
+    #[arg(long, default_value_t = 0)]
+    gas_tolerance: u64,
+
+    #[command(flatten)]
+    pool: PoolArgs,
+
+    #[command(flatten)]
+    volumes: VolumeArgs,
+}
+
+pub async fn verify(ctx: &Context, args: VerifyArgs) -> anyhow::Result<()> {
+    let volumes = args.volumes.volumes()?;
+
+    // Both backends have to read the state of the same block.
+    let block = ctx.block_number(&ctx.provider()?).await?;
+    let ctx = ctx.with_block(block);
+
+    let reference = PoolQuoter::new(&ctx, args.reference, args.pool.clone()).await?;
+    let candidate = PoolQuoter::new(&ctx, args.candidate, args.pool).await?;
+
+    let requests: Vec<_> = volumes
+        .into_iter()
+        .map(|volume| reference.request(volume))
+        .collect();
+    let gas_tolerance = [args.reference, args.candidate]
+        .iter()
+        .all(|backend| *backend != Backend::Native)
+        .then_some(args.gas_tolerance);
+
+    let report = verify_backends(
+        &reference.backend,
+        &candidate.backend,
+        &requests,
+        gas_tolerance,
+    )
+    .await?;
+
+    match ctx.format {
+        Format::Text => {
+            for divergence in &report.divergences {
+                for mismatch in &divergence.mismatches {
+                    println!("{}: {}", divergence.request.amount_in, describe(mismatch));
+                }
+            }
+            println!(
+                "{:?} vs {:?} at block {block}: {} quotes, {} reverts, {} divergent, max gas delta {}",
+                args.reference,
+                args.candidate,
+                report.quotes,
+                report.reverts,
+                report.divergences.len(),
+                report.max_gas_delta
+            );
+        }
+        Format::Json => {
+            let divergences: Vec<_> = report
+                .divergences
+                .iter()
+                .map(|divergence| {
+                    let mismatches: Vec<_> = divergence.mismatches.iter().map(describe).collect();
+                    json!({
+                        "amount_in": divergence.request.amount_in.to_string(),
+                        "mismatches": mismatches,
+                    })
+                })
+                .collect();
+            let report = json!({
+                "reference": format!("{:?}", args.reference),
+                "candidate": format!("{:?}", args.candidate),
+                "block": block,
+                "quotes": report.quotes,
+                "reverts": report.reverts,
+                "max_gas_delta": report.max_gas_delta.to_string(),
+                "divergences": divergences,
+            });
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        }
+    }
+
+    if !report.is_ok() {
+        bail!("{} divergent quotes", report.divergences.len());
+    }
+    Ok(())
+}
+
+fn describe(mismatch: &Mismatch) -> String {
+    match mismatch {
+        Mismatch::AmountOut { expected, actual } => {
+            format!("amount out {actual} != {expected}")
+        }
+        Mismatch::GasEstimate { expected, actual } => {
+            format!("gas estimate {actual} != {expected}")
+        }
+        Mismatch::TicksCrossed { expected, actual } => {
+            format!("ticks crossed {actual} != {expected}")
+        }
+        Mismatch::SqrtPriceAfter { expected, actual } => {
+            format!("sqrt price after {actual} != {expected}")
+        }
+        Mismatch::PartialFill { expected, actual } => {
+            format!("partial fill {actual} != {expected}")
+        }
+        Mismatch::Revert {
+            expected: None,
+            actual: Some(reason),
+        } => format!("candidate reverted ({reason}), reference quoted"),
+        Mismatch::Revert {
+            expected: Some(reason),
+            actual: None,
+        } => format!("reference reverted ({reason}), candidate quoted"),
+        Mismatch::Revert {
+            expected: Some(expected),
+            actual: Some(actual),
+        } => format!("both reverted, candidate ({actual}), reference ({expected})"),
+        Mismatch::Revert {
+            expected: None,
+            actual: None,
+        } => "both quoted, reported as a revert".to_string(),
+        Mismatch::RevertReason { expected, actual } => {
+            format!("revert reason ({actual}) != ({expected})")
+        }
+    }
+}
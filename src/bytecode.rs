@@ -0,0 +1,416 @@
+//! Named contract bytecode, checked against the chain so stale copies can't skew results.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use alloy::{
+    eips::BlockId,
+    primitives::{Address, B256, Bytes, keccak256},
+    providers::Provider,
+};
+use anyhow::anyhow;
+use revm::{primitives::KECCAK_EMPTY, state::Bytecode};
+
+use crate::{
+    address::{USDC_ADDR, WETH_ADDR},
+    cache::EvmCache,
+    revm::RevmProvider,
+};
+
+/// Where the artifacts are kept in the repository.
+pub const BYTECODE_DIR: &str = "src/bytecode";
+
+/// Foundry project of the contracts that only exist in simulations.
+pub const FOUNDRY_ROOT: &str = "contracts";
+
+/// Where `forge build` writes the artifacts of `contracts/`.
+pub const FOUNDRY_OUT: &str = "contracts/out";
+
+/// Artifacts built from source, as `(name, source file, contract)`.
+const FOUNDRY_ARTIFACTS: [(&str, &str, &str); 1] =
+    [("uni_v3_quoter", "UniV3Quoter.sol", "UniV3Quoter")];
+
+const EMBEDDED: [(&str, &str); 3] = [
+    ("usdc", include_str!("bytecode/usdc.hex")),
+    ("weth", include_str!("bytecode/weth.hex")),
+    ("uni_v3_quoter", include_str!("bytecode/uni_v3_quoter.hex")),
+];
+
+/// Returns the address of the on-chain copy of a known artifact,
+/// `None` for contracts that only exist in simulations.
+pub fn known_address(name: &str) -> Option<Address> {
+    match name {
+        "usdc" => Some(USDC_ADDR),
+        "weth" => Some(WETH_ADDR),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub name: String,
+    pub address: Option<Address>,
+    pub bytecode: Bytecode,
+}
+
+#[derive(Debug)]
+pub struct BytecodeMismatch {
+    pub name: String,
+    pub address: Address,
+    pub expected: B256,
+    pub actual: B256,
+}
+
+/// Artifact whose copy differs from the one built from source, hashes without the metadata.
+#[derive(Debug)]
+pub struct SourceMismatch {
+    pub name: String,
+    pub built: B256,
+    pub local: B256,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BytecodeRegistry {
+    artifacts: BTreeMap<String, Artifact>,
+}
+
+impl BytecodeRegistry {
+    /// Artifacts compiled into the binary.
+    pub fn embedded() -> anyhow::Result<Self> {
+        let mut registry = Self::default();
+        for (name, hex) in EMBEDDED {
+            registry.insert_hex(name, hex)?;
+        }
+        Ok(registry)
+    }
+
+    /// Loads every `<name>.hex` file of the directory.
+    pub fn from_dir(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut registry = Self::default();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "hex") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            registry.insert_hex(name, &std::fs::read_to_string(&path)?)?;
+        }
+        Ok(registry)
+    }
+
+    /// Loads the artifacts from `BYTECODE_DIR` if set, uses the embedded ones otherwise,
+    /// then replaces the ones built from source if `FOUNDRY_OUT` is set.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let registry = match std::env::var("BYTECODE_DIR") {
+            Ok(dir) => Self::from_dir(dir)?,
+            Err(_) => Self::embedded()?,
+        };
+        match std::env::var("FOUNDRY_OUT") {
+            Ok(out) => registry.with_foundry_out(out),
+            Err(_) => Ok(registry),
+        }
+    }
+
+    /// Replaces the artifacts built from `contracts/` with the `forge build` output,
+    /// e.g. `out/UniV3Quoter.sol/UniV3Quoter.json`.
+    pub fn with_foundry_out(mut self, out: impl AsRef<Path>) -> anyhow::Result<Self> {
+        for (name, file, contract) in FOUNDRY_ARTIFACTS {
+            let path = out.as_ref().join(file).join(format!("{contract}.json"));
+            self.insert(name, load_foundry_artifact(&path)?);
+        }
+        Ok(self)
+    }
+
+    pub fn insert(&mut self, name: &str, bytecode: Bytecode) {
+        let artifact = Artifact {
+            name: name.to_string(),
+            address: known_address(name),
+            bytecode,
+        };
+        self.artifacts.insert(name.to_string(), artifact);
+    }
+
+    fn insert_hex(&mut self, name: &str, hex: &str) -> anyhow::Result<()> {
+        let bytes = Bytes::from_str(hex.trim())
+            .map_err(|e| anyhow!("artifact {name} is not valid hex: {e}"))?;
+        self.insert(name, Bytecode::new_raw(bytes));
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> anyhow::Result<&Artifact> {
+        self.artifacts
+            .get(name)
+            .ok_or_else(|| anyhow!("unknown artifact {name}"))
+    }
+
+    pub fn bytecode(&self, name: &str) -> anyhow::Result<Bytecode> {
+        Ok(self.get(name)?.bytecode.clone())
+    }
+
+    pub fn artifacts(&self) -> impl Iterator<Item = &Artifact> {
+        self.artifacts.values()
+    }
+
+    /// Compares every artifact that has an on-chain copy with its code hash at the block.
+    ///
+    /// Code hashes are cached on disk per block.
+    pub async fn verify(
+        &self,
+        provider: &RevmProvider,
+        block: u64,
+        cache: &EvmCache,
+    ) -> anyhow::Result<Vec<BytecodeMismatch>> {
+        let mut mismatches = Vec::new();
+        for artifact in self.artifacts() {
+            let Some(address) = artifact.address else {
+                continue;
+            };
+            let expected = fetch_code_hash(provider, address, block, cache).await?;
+            let actual = keccak256(artifact.bytecode.original_byte_slice());
+            if actual != expected {
+                mismatches.push(BytecodeMismatch {
+                    name: artifact.name.clone(),
+                    address,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        Ok(mismatches)
+    }
+
+    /// Compares the artifacts built from `contracts/` with the `forge build` output.
+    ///
+    /// The trailing CBOR metadata is ignored, it changes with comments and paths.
+    pub fn verify_foundry_out(&self, out: impl AsRef<Path>) -> anyhow::Result<Vec<SourceMismatch>> {
+        let mut mismatches = Vec::new();
+        for (name, file, contract) in FOUNDRY_ARTIFACTS {
+            let path = out.as_ref().join(file).join(format!("{contract}.json"));
+            let built = load_foundry_artifact(&path)?;
+            let built = keccak256(strip_metadata(built.original_byte_slice()));
+            let local = keccak256(strip_metadata(
+                self.get(name)?.bytecode.original_byte_slice(),
+            ));
+            if built != local {
+                mismatches.push(SourceMismatch {
+                    name: name.to_string(),
+                    built,
+                    local,
+                });
+            }
+        }
+        Ok(mismatches)
+    }
+
+    /// Drops the artifacts whose on-chain copy differs, e.g. on a chain other than
+    /// mainnet or once they are stale, so their code is fetched instead.
+    pub async fn verified(
+        mut self,
+        provider: &RevmProvider,
+        block: u64,
+        cache: &EvmCache,
+    ) -> anyhow::Result<Self> {
+        for mismatch in self.verify(provider, block, cache).await? {
+            tracing::warn!(
+                "not using artifact {}: {} code hash at block {block} is {}, not {} \
+                 (cargo run --bin denegnet -- bytecode refresh)",
+                mismatch.name,
+                mismatch.address,
+                mismatch.expected,
+                mismatch.actual
+            );
+            self.artifacts.remove(&mismatch.name);
+        }
+        Ok(self)
+    }
+
+    /// Same as [`BytecodeRegistry::verify`], fails on the first stale artifact.
+    pub async fn ensure_verified(
+        &self,
+        provider: &RevmProvider,
+        block: u64,
+        cache: &EvmCache,
+    ) -> anyhow::Result<()> {
+        if let Some(mismatch) = self.verify(provider, block, cache).await?.first() {
+            return Err(anyhow!(
+                "artifact {} is stale: {} on-chain code hash at block {block} is {}, not {} \
+                 (cargo run --bin denegnet -- bytecode refresh)",
+                mismatch.name,
+                mismatch.address,
+                mismatch.expected,
+                mismatch.actual
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Fetches the on-chain code of every known artifact and (over)writes its `<name>.hex` file.
+///
+/// Returns the paths written.
+pub async fn refresh(
+    dir: impl AsRef<Path>,
+    provider: &RevmProvider,
+    block: BlockId,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+
+    let mut written = Vec::new();
+    for (name, _) in EMBEDDED {
+        let Some(address) = known_address(name) else {
+            continue;
+        };
+        // eth_getCode
+        let code = provider.get_code_at(address).block_id(block).await?;
+        let path = dir.join(format!("{name}.hex"));
+        std::fs::write(&path, format!("{code}\n"))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Writes the `<name>.hex` file of every artifact built from `contracts/`
+/// from the `forge build` output.
+///
+/// Returns the paths written.
+pub fn refresh_from_foundry_out(
+    dir: impl AsRef<Path>,
+    out: impl AsRef<Path>,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let dir = dir.as_ref();
+    std::fs::create_dir_all(dir)?;
+
+    let mut written = Vec::new();
+    for (name, file, contract) in FOUNDRY_ARTIFACTS {
+        let artifact = out.as_ref().join(file).join(format!("{contract}.json"));
+        let bytes = Bytes::copy_from_slice(load_foundry_artifact(&artifact)?.original_byte_slice());
+        let path = dir.join(format!("{name}.hex"));
+        std::fs::write(&path, format!("{bytes}\n"))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Runs `forge build` in the Foundry project with the settings of its `foundry.toml`.
+pub fn forge_build(root: impl AsRef<Path>) -> anyhow::Result<()> {
+    let root = root.as_ref();
+    let status = std::process::Command::new("forge")
+        .arg("build")
+        .arg("--root")
+        .arg(root)
+        .status()
+        .map_err(|e| anyhow!("failed to run forge (is Foundry installed?): {e}"))?;
+    if !status.success() {
+        return Err(anyhow!(
+            "forge build in {} failed: {status}",
+            root.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Drops the CBOR metadata solc appends to the runtime code, its length is in the last 2 bytes.
+fn strip_metadata(code: &[u8]) -> &[u8] {
+    let Some((rest, length)) = code.split_last_chunk::<2>() else {
+        return code;
+    };
+    let length = u16::from_be_bytes(*length) as usize;
+    match rest.len().checked_sub(length) {
+        // The metadata is a CBOR map.
+        Some(end) if rest[end] & 0xe0 == 0xa0 => &rest[..end],
+        _ => code,
+    }
+}
+
+/// Reads the deployed bytecode of a Foundry artifact.
+pub fn load_foundry_artifact(path: impl AsRef<Path>) -> anyhow::Result<Bytecode> {
+    let path = path.as_ref();
+    let artifact =
+        std::fs::read(path).map_err(|e| anyhow!("failed to read {}: {e}", path.display()))?;
+    let artifact: serde_json::Value = serde_json::from_slice(&artifact)?;
+    let object = artifact["deployedBytecode"]["object"]
+        .as_str()
+        .ok_or_else(|| anyhow!("{} has no deployed bytecode", path.display()))?;
+    let bytes = Bytes::from_str(object)
+        .map_err(|e| anyhow!("{} has invalid bytecode: {e}", path.display()))?;
+    Ok(Bytecode::new_raw(bytes))
+}
+
+async fn fetch_code_hash(
+    provider: &RevmProvider,
+    address: Address,
+    block: u64,
+    cache: &EvmCache,
+) -> anyhow::Result<B256> {
+    let cache_key = format!("code_hash-{address:?}-{block}");
+    if let Ok(bytes) = cache.read(&cache_key).await {
+        return Ok(serde_json::from_slice(&bytes)?);
+    }
+
+    let proof = provider
+        .get_proof(address, vec![])
+        .block_id(BlockId::number(block))
+        .await?;
+    let code_hash = match proof.code_hash {
+        B256::ZERO => KECCAK_EMPTY,
+        code_hash => code_hash,
+    };
+
+    cache
+        .write(&cache_key, serde_json::to_vec(&code_hash)?)
+        .await?;
+    Ok(code_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::hex;
+
+    use super::*;
+
+    #[test]
+    fn strips_cbor_metadata() {
+        // `a2 64 "ipfs" 42 <2 bytes>` is 10 bytes, followed by its length.
+        let code = hex!("6080604052a264697066735842beef000a");
+        assert_eq!(strip_metadata(&code), hex!("6080604052"));
+    }
+
+    #[test]
+    fn keeps_code_without_metadata() {
+        let code = hex!("6080604052");
+        assert_eq!(strip_metadata(&code), code);
+        assert_eq!(strip_metadata(&[0x00]), [0x00]);
+    }
+
+    #[test]
+    fn embedded_quoter_has_metadata() {
+        let registry = BytecodeRegistry::embedded().unwrap();
+        let code = registry
+            .get("uni_v3_quoter")
+            .unwrap()
+            .bytecode
+            .original_bytes();
+        assert!(strip_metadata(&code).len() < code.len());
+    }
+
+    /// `bytecode --build verify`: the embedded quoter must be the build of `contracts/`.
+    #[test]
+    #[ignore = "requires forge"]
+    fn embedded_quoter_matches_source() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        forge_build(root.join(FOUNDRY_ROOT)).unwrap();
+
+        let registry = BytecodeRegistry::embedded().unwrap();
+        let mismatches = registry.verify_foundry_out(root.join(FOUNDRY_OUT)).unwrap();
+        assert!(
+            mismatches.is_empty(),
+            "{mismatches:?}, run `bytecode --build refresh`"
+        );
+    }
+}
@@ -0,0 +1,486 @@
+use std::path::{Path, PathBuf};
+
+use alloy::{
+    eips::{BlockId, BlockNumberOrTag},
+    providers::Provider,
+};
+use revm::{
+    DatabaseRef,
+    database::EmptyDB,
+    primitives::{Address, B256, KECCAK_EMPTY, StorageKey, StorageValue, keccak256},
+    state::{AccountInfo, Bytecode},
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::revm::RevmProvider;
+
+pub const CACHE_DIR: &str = ".evm_cache";
+
+/// On-disk cache location with every key namespaced by chain id,
+/// so a fork or an L2 never gets served mainnet entries.
+#[derive(Debug, Clone)]
+pub struct EvmCache {
+    dir: PathBuf,
+    chain_id: u64,
+}
+
+impl EvmCache {
+    pub fn new(dir: impl Into<PathBuf>, chain_id: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            chain_id,
+        }
+    }
+
+    /// Reads the location from `EVM_CACHE_DIR` (defaults to [`CACHE_DIR`])
+    /// and the chain id from `CHAIN_ID`, falling back to `eth_chainId`.
+    pub async fn from_env(provider: &RevmProvider) -> anyhow::Result<Self> {
+        let dir = std::env::var("EVM_CACHE_DIR").unwrap_or_else(|_| CACHE_DIR.to_string());
+        let chain_id = match std::env::var("CHAIN_ID") {
+            Ok(chain_id) => chain_id.parse()?,
+            Err(_) => provider.get_chain_id().await?,
+        };
+        Ok(Self::new(dir, chain_id))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    pub fn key(&self, key: &str) -> String {
+        format!("{}/{key}", self.chain_id)
+    }
+
+    pub async fn read(&self, key: &str) -> cacache::Result<Vec<u8>> {
+        cacache::read(&self.dir, self.key(key)).await
+    }
+
+    pub async fn write(&self, key: &str, data: impl AsRef<[u8]>) -> cacache::Result<()> {
+        cacache::write(&self.dir, self.key(key), data).await?;
+        Ok(())
+    }
+
+    pub fn read_sync(&self, key: &str) -> cacache::Result<Vec<u8>> {
+        cacache::read_sync(&self.dir, self.key(key))
+    }
+
+    pub fn write_sync(&self, key: &str, data: impl AsRef<[u8]>) -> cacache::Result<()> {
+        cacache::write_sync(&self.dir, self.key(key), data)?;
+        Ok(())
+    }
+
+    /// Lists entries of this chain, keys are returned without the namespace.
+    pub fn entries(&self) -> impl Iterator<Item = cacache::Result<cacache::Metadata>> + '_ {
+        let prefix = self.key("");
+        // Listing a missing cache directory fails, treat it as empty.
+        let entries = self.dir.exists().then(|| cacache::list_sync(&self.dir));
+        entries
+            .into_iter()
+            .flatten()
+            .filter_map(move |entry| match entry {
+                Ok(mut entry) => {
+                    let key = entry.key.strip_prefix(&prefix)?.to_string();
+                    entry.key = key;
+                    Some(Ok(entry))
+                }
+                Err(e) => Some(Err(e)),
+            })
+    }
+
+    /// Removes block-scoped entries stored for blocks older than `keep_from`,
+    /// returns the number of removed entries.
+    ///
+    /// Only index entries are removed, the content is deduplicated
+    /// by hash and may still be referenced by newer blocks.
+    pub fn prune(&self, keep_from: u64) -> anyhow::Result<usize> {
+        self.remove_where(|key| key_block(key).is_some_and(|block| block < keep_from))
+    }
+
+    /// Removes all entries of this chain.
+    pub fn purge(&self) -> anyhow::Result<usize> {
+        self.remove_where(|_| true)
+    }
+
+    /// Re-hashes cached bytecode against its code hash and returns the broken entries.
+    ///
+    /// Account infos are checked against the code hash they were stored with,
+    /// `bytecode-*` entries against the current on-chain code hash when
+    /// a provider is given.
+    pub async fn verify(&self, provider: Option<&RevmProvider>) -> anyhow::Result<Vec<CacheIssue>> {
+        let mut issues = Vec::new();
+
+        for entry in self.entries() {
+            let key = entry?.key;
+            let issue = |reason: String| CacheIssue {
+                key: key.clone(),
+                reason,
+            };
+
+            // cacache checks the content integrity on read.
+            let bytes = match self.read(&key).await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    issues.push(issue(e.to_string()));
+                    continue;
+                }
+            };
+
+            if key.starts_with("basic-") {
+                let info: Option<AccountInfo> = match serde_json::from_slice(&bytes) {
+                    Ok(info) => info,
+                    Err(e) => {
+                        issues.push(issue(e.to_string()));
+                        continue;
+                    }
+                };
+                if let Some(info) = info
+                    && let Some(code) = info.code
+                    && code.hash_slow() != info.code_hash
+                {
+                    issues.push(issue(format!("code hash mismatch {}", info.code_hash)));
+                }
+            } else if let Some(address) = key.strip_prefix("bytecode-")
+                && let Some(provider) = provider
+            {
+                let address: Address = address.parse()?;
+                let proof = provider.get_proof(address, vec![]).await?;
+                let expected = match proof.code_hash {
+                    B256::ZERO => KECCAK_EMPTY,
+                    code_hash => code_hash,
+                };
+                let actual = keccak256(&bytes);
+                if actual != expected {
+                    issues.push(issue(format!(
+                        "stale bytecode {actual}, on-chain {expected}"
+                    )));
+                }
+            }
+        }
+
+        Ok(issues)
+    }
+
+    fn remove_where(&self, predicate: impl Fn(&str) -> bool) -> anyhow::Result<usize> {
+        let mut removed = 0;
+        for entry in self.entries() {
+            let entry = entry?;
+            if predicate(&entry.key) {
+                cacache::remove_sync(&self.dir, self.key(&entry.key))?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[derive(Debug)]
+pub struct CacheIssue {
+    pub key: String,
+    pub reason: String,
+}
+
+/// Persistent layer between `CacheDB` and the RPC-backed database.
+///
+/// Every `basic`, `storage` and `block_hash` result is stored on disk
+/// under the pinned block number, so restarting against the same block
+/// doesn't hit the network. Reads at a block tag (e.g. "latest") are not cached.
+#[derive(Debug)]
+pub struct DiskCache<DB> {
+    db: DB,
+    cache: EvmCache,
+    block: Option<u64>,
+}
+
+impl<DB> DiskCache<DB> {
+    pub fn new(db: DB, cache: EvmCache, block: BlockId) -> Self {
+        let block = match block {
+            BlockId::Number(BlockNumberOrTag::Number(number)) => Some(number),
+            _ => None,
+        };
+
+        Self { db, cache, block }
+    }
+
+    pub fn cache(&self) -> &EvmCache {
+        &self.cache
+    }
+
+    pub fn block(&self) -> Option<u64> {
+        self.block
+    }
+
+    fn cached<T, E>(&self, key: String, fetch: impl FnOnce() -> Result<T, E>) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let Some(block) = self.block else {
+            return fetch();
+        };
+        let key = format!("{key}-{block}");
+
+        if let Ok(bytes) = self.cache.read_sync(&key) {
+            match serde_json::from_slice(&bytes) {
+                Ok(value) => return Ok(value),
+                Err(e) => tracing::warn!("corrupted cache entry {key}: {e}"),
+            }
+        }
+
+        let value = fetch()?;
+        let bytes = serde_json::to_vec(&value).expect("serializable value");
+        if let Err(e) = self.cache.write_sync(&key, bytes) {
+            tracing::warn!("failed to write cache entry {key}: {e}");
+        }
+
+        Ok(value)
+    }
+}
+
+impl<DB: DatabaseRef> DatabaseRef for DiskCache<DB> {
+    type Error = DB::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        self.cached(format!("basic-{address:?}"), || self.db.basic_ref(address))
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        // Code is returned along with the account info by `basic_ref`.
+        self.db.code_by_hash_ref(code_hash)
+    }
+
+    fn storage_ref(
+        &self,
+        address: Address,
+        index: StorageKey,
+    ) -> Result<StorageValue, Self::Error> {
+        self.cached(format!("storage-{address:?}-{index:#x}"), || {
+            self.db.storage_ref(address, index)
+        })
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        self.cached(format!("block_hash-{number}"), || {
+            self.db.block_hash_ref(number)
+        })
+    }
+}
+
+/// Database that may keep results on disk, e.g. the storage slots found by
+/// [`crate::erc20::find_balance_slot`].
+pub trait PersistentCache {
+    fn evm_cache(&self) -> Option<&EvmCache>;
+}
+
+impl<DB> PersistentCache for DiskCache<DB> {
+    fn evm_cache(&self) -> Option<&EvmCache> {
+        Some(&self.cache)
+    }
+}
+
+/// Nothing is persisted for in-memory state, e.g. in tests.
+impl PersistentCache for EmptyDB {
+    fn evm_cache(&self) -> Option<&EvmCache> {
+        None
+    }
+}
+
+/// Returns the block number a block-scoped cache key was stored under.
+pub fn key_block(key: &str) -> Option<u64> {
+    let (_, block) = key.rsplit_once('-')?;
+    // `u64::from_str` also takes a leading `+`.
+    if !block.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    block.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Bytes, U256, address};
+    use revm::database::CacheDB;
+
+    use super::*;
+
+    const ACCOUNT: Address = address!("0000000000000000000000000000000000001234");
+
+    fn temp_cache(name: &str, chain_id: u64) -> EvmCache {
+        let dir =
+            std::env::temp_dir().join(format!("denegnet-cache-{name}-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        EvmCache::new(dir, chain_id)
+    }
+
+    fn keys(cache: &EvmCache) -> Vec<String> {
+        let mut keys: Vec<_> = cache.entries().map(|entry| entry.unwrap().key).collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn serves_restarts_from_disk() {
+        let cache = temp_cache("restart", 1);
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x00]));
+        let info = AccountInfo {
+            balance: U256::from(7),
+            nonce: 3,
+            code_hash: code.hash_slow(),
+            code: Some(code),
+        };
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(ACCOUNT, info.clone());
+        db.insert_account_storage(ACCOUNT, U256::from(1), U256::from(42))
+            .unwrap();
+        db.cache
+            .block_hashes
+            .insert(U256::from(99), B256::repeat_byte(0x99));
+
+        let warm = DiskCache::new(db, cache.clone(), BlockId::number(100));
+        assert_eq!(warm.basic_ref(ACCOUNT).unwrap(), Some(info.clone()));
+        assert_eq!(
+            warm.storage_ref(ACCOUNT, U256::from(1)).unwrap(),
+            U256::from(42)
+        );
+        assert_eq!(warm.block_hash_ref(99).unwrap(), B256::repeat_byte(0x99));
+
+        // An empty database knows nothing, everything comes from disk.
+        let cold = DiskCache::new(EmptyDB::default(), cache.clone(), BlockId::number(100));
+        assert_eq!(cold.basic_ref(ACCOUNT).unwrap(), Some(info));
+        assert_eq!(
+            cold.storage_ref(ACCOUNT, U256::from(1)).unwrap(),
+            U256::from(42)
+        );
+        assert_eq!(cold.block_hash_ref(99).unwrap(), B256::repeat_byte(0x99));
+
+        // Another block, or no block at all, doesn't see those entries.
+        let next = DiskCache::new(EmptyDB::default(), cache.clone(), BlockId::number(101));
+        assert_eq!(next.basic_ref(ACCOUNT).unwrap(), None);
+        let latest = DiskCache::new(EmptyDB::default(), cache.clone(), BlockId::latest());
+        assert_eq!(
+            latest.storage_ref(ACCOUNT, U256::from(1)).unwrap(),
+            U256::ZERO
+        );
+
+        std::fs::remove_dir_all(cache.dir()).ok();
+    }
+
+    #[test]
+    fn prunes_only_older_blocks() {
+        let cache = temp_cache("prune", 1);
+        let token = format!("{ACCOUNT:?}");
+        for key in [
+            format!("storage-{token}-0x1-100"),
+            format!("basic-{token}-149"),
+            format!("basic-{token}-150"),
+            "block_env-200".to_string(),
+            format!("bytecode-{token}"),
+            format!("balance_slot-{token}-0x{}", "ab".repeat(32)),
+        ] {
+            cache.write_sync(&key, b"{}").unwrap();
+        }
+
+        assert_eq!(cache.prune(150).unwrap(), 2);
+        assert_eq!(
+            keys(&cache),
+            [
+                format!("balance_slot-{token}-0x{}", "ab".repeat(32)),
+                format!("basic-{token}-150"),
+                "block_env-200".to_string(),
+                format!("bytecode-{token}"),
+            ]
+        );
+
+        std::fs::remove_dir_all(cache.dir()).ok();
+    }
+
+    #[test]
+    fn isolates_chains() {
+        let mainnet = temp_cache("chains", 1);
+        let optimism = EvmCache::new(mainnet.dir(), 10);
+
+        mainnet.write_sync("block_env-100", b"mainnet").unwrap();
+        optimism.write_sync("block_env-100", b"optimism").unwrap();
+        optimism.write_sync("block_env-101", b"optimism").unwrap();
+
+        assert_eq!(mainnet.read_sync("block_env-100").unwrap(), b"mainnet");
+        assert_eq!(optimism.read_sync("block_env-100").unwrap(), b"optimism");
+        assert!(mainnet.read_sync("block_env-101").is_err());
+        assert_eq!(keys(&mainnet), ["block_env-100"]);
+        assert_eq!(keys(&optimism), ["block_env-100", "block_env-101"]);
+
+        std::fs::remove_dir_all(mainnet.dir()).ok();
+    }
+
+    #[test]
+    fn purges_only_its_chain() {
+        let mainnet = temp_cache("purge", 1);
+        let optimism = EvmCache::new(mainnet.dir(), 10);
+        mainnet.write_sync("block_env-100", b"mainnet").unwrap();
+        optimism.write_sync("block_env-100", b"optimism").unwrap();
+        optimism.write_sync("bytecode-0x1234", b"optimism").unwrap();
+
+        assert_eq!(optimism.purge().unwrap(), 2);
+        assert_eq!(keys(&optimism), Vec::<String>::new());
+        assert_eq!(keys(&mainnet), ["block_env-100"]);
+        assert_eq!(mainnet.read_sync("block_env-100").unwrap(), b"mainnet");
+
+        std::fs::remove_dir_all(mainnet.dir()).ok();
+    }
+
+    #[tokio::test]
+    async fn verify_flags_code_hash_mismatch() {
+        let cache = temp_cache("verify", 1);
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x00]));
+        let valid = AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code.clone()),
+            ..Default::default()
+        };
+        let corrupted = AccountInfo {
+            code_hash: keccak256([0x01]),
+            ..valid.clone()
+        };
+        cache
+            .write_sync(
+                "basic-0x1234-100",
+                serde_json::to_vec(&Some(valid)).unwrap(),
+            )
+            .unwrap();
+        cache
+            .write_sync(
+                "basic-0x1234-101",
+                serde_json::to_vec(&Some(corrupted)).unwrap(),
+            )
+            .unwrap();
+        // Empty accounts and other entries are not checked without a provider.
+        cache
+            .write_sync(
+                "basic-0x5678-100",
+                serde_json::to_vec(&None::<AccountInfo>).unwrap(),
+            )
+            .unwrap();
+        cache.write_sync("bytecode-0x1234", [0x01]).unwrap();
+
+        let issues = cache.verify(None).await.unwrap();
+        assert_eq!(issues.len(), 1, "{issues:?}");
+        assert_eq!(issues[0].key, "basic-0x1234-101");
+        assert!(issues[0].reason.contains("code hash mismatch"));
+
+        std::fs::remove_dir_all(cache.dir()).ok();
+    }
+
+    #[test]
+    fn parses_only_decimal_key_blocks() {
+        assert_eq!(key_block("block_env-19000000"), Some(19_000_000));
+        assert_eq!(key_block("storage-0x1234-0x10-7"), Some(7));
+
+        assert_eq!(key_block("bytecode-0x1234"), None);
+        assert_eq!(key_block("balance_slot-0x1234-0xabcd"), None);
+        assert_eq!(key_block("block_env-12a"), None);
+        assert_eq!(key_block("block_env-+12"), None);
+        assert_eq!(key_block("block_env-"), None);
+        assert_eq!(key_block("block_env"), None);
+    }
+}
@@ -0,0 +1,343 @@
+//! Mocking ERC20 state without knowing the storage layout of the token.
+
+use alloy::{
+    primitives::{Address, Bytes, U256, address, keccak256},
+    sol_types::SolValue,
+};
+use anyhow::anyhow;
+use revm::{Database, DatabaseRef, database::CacheDB};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    abi::{
+        allowance_calldata, balance_of_calldata, decode_allowance_response,
+        decode_balance_of_response, decode_implementation_response, implementation_calldata,
+    },
+    cache::PersistentCache,
+    error::SimError,
+    revm::{MAX_PROXY_DEPTH, ProxyKind, Simulator, proxy_slot},
+};
+
+/// Mapping slots probed by [`find_balance_slot`] and [`find_allowance_slot`].
+const MAX_PROBED_SLOT: u64 = 100;
+
+/// Holder whose balance and allowance are overwritten while probing, never a real account.
+const PROBE_HOLDER: Address = address!("00000000000000000000000000000000000b0b0b");
+const PROBE_SPENDER: Address = address!("00000000000000000000000000000000000c0c0c");
+
+/// Unlikely to be a real balance, fits into packed `uint128` balances.
+const SENTINEL: U256 = U256::from_limbs([0x0bad_cafe_0bad_cafe, 0x1337, 0, 0]);
+
+/// Order in which the compiler hashes the key and the slot of a mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MappingLayout {
+    /// `keccak256(key . slot)`
+    Solidity,
+    /// `keccak256(slot . key)`
+    Vyper,
+}
+
+/// Storage slot of a `mapping(address => uint256)`
+/// or a `mapping(address => mapping(address => uint256))`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MappingSlot {
+    pub slot: U256,
+    pub layout: MappingLayout,
+}
+
+impl MappingSlot {
+    /// Returns the storage slot that holds the value of `key`.
+    pub fn storage_slot(&self, key: Address) -> U256 {
+        let hash = match self.layout {
+            MappingLayout::Solidity => keccak256((key, self.slot).abi_encode()),
+            MappingLayout::Vyper => keccak256((self.slot, key).abi_encode()),
+        };
+        hash.into()
+    }
+
+    /// Returns the storage slot that holds the value of `[key][inner_key]`.
+    pub fn nested_storage_slot(&self, key: Address, inner_key: Address) -> U256 {
+        let inner = MappingSlot {
+            slot: self.storage_slot(key),
+            layout: self.layout,
+        };
+        inner.storage_slot(inner_key)
+    }
+}
+
+/// Finds the slot of the `balanceOf` mapping of the token.
+///
+/// Writes a sentinel balance to every candidate slot and checks it with
+/// `balanceOf`, storage of proxies is probed the same way since it belongs
+/// to the proxy. The slot found is cached on disk (if the database has a
+/// [`PersistentCache`]) per token and implementation code hash.
+pub fn find_balance_slot<ExtDB>(
+    simulator: &mut Simulator<CacheDB<ExtDB>>,
+    token: Address,
+) -> anyhow::Result<MappingSlot>
+where
+    ExtDB: DatabaseRef + PersistentCache,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    find_mapping_slot(
+        simulator,
+        token,
+        "balance_slot",
+        |mapping_slot| mapping_slot.storage_slot(PROBE_HOLDER),
+        balance_of_calldata(PROBE_HOLDER),
+        decode_balance_of_response,
+    )
+}
+
+/// Finds the slot of the `allowance` mapping of the token, same as [`find_balance_slot`].
+pub fn find_allowance_slot<ExtDB>(
+    simulator: &mut Simulator<CacheDB<ExtDB>>,
+    token: Address,
+) -> anyhow::Result<MappingSlot>
+where
+    ExtDB: DatabaseRef + PersistentCache,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    find_mapping_slot(
+        simulator,
+        token,
+        "allowance_slot",
+        |mapping_slot| mapping_slot.nested_storage_slot(PROBE_HOLDER, PROBE_SPENDER),
+        allowance_calldata(PROBE_HOLDER, PROBE_SPENDER),
+        decode_allowance_response,
+    )
+}
+
+/// Sets the token balance of `holder`, see [`find_balance_slot`].
+///
+/// The balance is a storage override, kept when the simulator advances to a later block.
+pub fn set_erc20_balance<ExtDB>(
+    simulator: &mut Simulator<CacheDB<ExtDB>>,
+    token: Address,
+    holder: Address,
+    amount: U256,
+) -> anyhow::Result<()>
+where
+    ExtDB: DatabaseRef + PersistentCache,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let mapping_slot = find_balance_slot(simulator, token)?;
+    simulator.override_storage(token, mapping_slot.storage_slot(holder), amount)?;
+    Ok(())
+}
+
+/// Sets the amount `spender` is allowed to transfer from `owner`, see [`find_allowance_slot`].
+///
+/// Kept when the simulator advances to a later block, like [`set_erc20_balance`].
+pub fn set_erc20_allowance<ExtDB>(
+    simulator: &mut Simulator<CacheDB<ExtDB>>,
+    token: Address,
+    owner: Address,
+    spender: Address,
+    amount: U256,
+) -> anyhow::Result<()>
+where
+    ExtDB: DatabaseRef + PersistentCache,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let mapping_slot = find_allowance_slot(simulator, token)?;
+    simulator.override_storage(
+        token,
+        mapping_slot.nested_storage_slot(owner, spender),
+        amount,
+    )?;
+    Ok(())
+}
+
+fn find_mapping_slot<ExtDB>(
+    simulator: &mut Simulator<CacheDB<ExtDB>>,
+    token: Address,
+    name: &str,
+    storage_slot: impl Fn(&MappingSlot) -> U256,
+    calldata: Bytes,
+    decode: fn(Bytes) -> Result<U256, SimError>,
+) -> anyhow::Result<MappingSlot>
+where
+    ExtDB: DatabaseRef + PersistentCache,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    // The layout is the implementation's, upgrading a proxy may change it.
+    let implementation = resolve_implementation(simulator, token)?;
+    let code_hash = simulator
+        .db_mut()
+        .basic(implementation)?
+        .unwrap_or_default()
+        .code_hash;
+    let cache = simulator.db().db.evm_cache().cloned();
+    let cache_key = format!("{name}-{token:?}-{code_hash:?}");
+    if let Some(cache) = &cache
+        && let Ok(bytes) = cache.read_sync(&cache_key)
+    {
+        return Ok(serde_json::from_slice(&bytes)?);
+    }
+
+    for slot in 0..MAX_PROBED_SLOT {
+        for layout in [MappingLayout::Solidity, MappingLayout::Vyper] {
+            let mapping_slot = MappingSlot {
+                slot: U256::from(slot),
+                layout,
+            };
+            // The probe holder never holds tokens, no need to fetch the value to restore.
+            let storage_slot = storage_slot(&mapping_slot);
+            simulator
+                .db_mut()
+                .insert_account_storage(token, storage_slot, SENTINEL)?;
+            let response = simulator.call(token, calldata.clone());
+            simulator
+                .db_mut()
+                .insert_account_storage(token, storage_slot, U256::ZERO)?;
+
+            let value = match response {
+                Ok(response) => decode(response).ok(),
+                Err(err) if err.is_database() => return Err(err.into()),
+                // A sentinel in the wrong slot may well break the call.
+                Err(_) => None,
+            };
+            if value == Some(SENTINEL) {
+                if let Some(cache) = &cache {
+                    cache.write_sync(&cache_key, serde_json::to_vec(&mapping_slot)?)?;
+                }
+                return Ok(mapping_slot);
+            }
+        }
+    }
+
+    Err(anyhow!("{name} of {token} not found"))
+}
+
+/// Follows the proxies of the token down to the contract with its code,
+/// the token itself if it is not a proxy.
+fn resolve_implementation<ExtDB>(
+    simulator: &mut Simulator<CacheDB<ExtDB>>,
+    token: Address,
+) -> anyhow::Result<Address>
+where
+    ExtDB: DatabaseRef + PersistentCache,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let mut implementation = token;
+    for _ in 0..MAX_PROXY_DEPTH {
+        implementation = match proxy_slot(simulator.db_mut(), implementation)? {
+            Some((ProxyKind::Beacon, beacon)) => {
+                let response = simulator.call(beacon, implementation_calldata())?;
+                decode_implementation_response(response)?
+            }
+            Some((_, address)) => address,
+            None => break,
+        };
+    }
+    Ok(implementation)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Bytes, address};
+    use revm::database::EmptyDB;
+
+    use super::*;
+    use crate::test_support::{HashOrder, insert_code, token_code};
+
+    const TOKEN: Address = address!("0000000000000000000000000000000000001234");
+    const HOLDER: Address = address!("000000000000000000000000000000000000beef");
+    const SPENDER: Address = address!("000000000000000000000000000000000000cafe");
+
+    /// Runtime code of a token with `balanceOf` at `balance_slot` and
+    /// `allowance` at `allowance_slot`, the storage layout of `layout`.
+    fn erc20_code(layout: MappingLayout, balance_slot: u8, allowance_slot: u8) -> Bytes {
+        let order = match layout {
+            MappingLayout::Solidity => HashOrder::KeyFirst,
+            MappingLayout::Vyper => HashOrder::SlotFirst,
+        };
+        token_code(order, balance_slot, allowance_slot)
+    }
+
+    fn simulator(code: Bytes) -> Simulator<CacheDB<EmptyDB>> {
+        let mut db = CacheDB::new(EmptyDB::default());
+        insert_code(&mut db, TOKEN, code);
+        Simulator::new(db)
+    }
+
+    fn balance_of(simulator: &mut Simulator<CacheDB<EmptyDB>>, holder: Address) -> U256 {
+        let response = simulator.call(TOKEN, balance_of_calldata(holder)).unwrap();
+        decode_balance_of_response(response).unwrap()
+    }
+
+    #[test]
+    fn finds_solidity_balance_slot() {
+        let mut simulator = simulator(erc20_code(MappingLayout::Solidity, 3, 4));
+
+        assert_eq!(
+            find_balance_slot(&mut simulator, TOKEN).unwrap(),
+            MappingSlot {
+                slot: U256::from(3),
+                layout: MappingLayout::Solidity,
+            }
+        );
+
+        let amount = U256::from(1_000_000_000_000_000_000_u64);
+        set_erc20_balance(&mut simulator, TOKEN, HOLDER, amount).unwrap();
+        assert_eq!(balance_of(&mut simulator, HOLDER), amount);
+        // The probe leaves no balance behind.
+        assert_eq!(balance_of(&mut simulator, PROBE_HOLDER), U256::ZERO);
+    }
+
+    #[test]
+    fn finds_vyper_balance_slot() {
+        let mut simulator = simulator(erc20_code(MappingLayout::Vyper, 1, 2));
+
+        assert_eq!(
+            find_balance_slot(&mut simulator, TOKEN).unwrap(),
+            MappingSlot {
+                slot: U256::from(1),
+                layout: MappingLayout::Vyper,
+            }
+        );
+
+        set_erc20_balance(&mut simulator, TOKEN, HOLDER, U256::from(42)).unwrap();
+        assert_eq!(balance_of(&mut simulator, HOLDER), U256::from(42));
+    }
+
+    fn allowance(
+        simulator: &mut Simulator<CacheDB<EmptyDB>>,
+        owner: Address,
+        spender: Address,
+    ) -> U256 {
+        let response = simulator
+            .call(TOKEN, allowance_calldata(owner, spender))
+            .unwrap();
+        decode_allowance_response(response).unwrap()
+    }
+
+    #[test]
+    fn sets_allowance_in_nested_mapping() {
+        for layout in [MappingLayout::Solidity, MappingLayout::Vyper] {
+            let mut simulator = simulator(erc20_code(layout, 3, 4));
+
+            assert_eq!(
+                find_allowance_slot(&mut simulator, TOKEN).unwrap(),
+                MappingSlot {
+                    slot: U256::from(4),
+                    layout,
+                }
+            );
+
+            set_erc20_allowance(&mut simulator, TOKEN, HOLDER, SPENDER, U256::MAX).unwrap();
+            assert_eq!(allowance(&mut simulator, HOLDER, SPENDER), U256::MAX);
+            // Only that pair is approved, the balance is untouched.
+            assert_eq!(allowance(&mut simulator, SPENDER, HOLDER), U256::ZERO);
+            assert_eq!(balance_of(&mut simulator, HOLDER), U256::ZERO);
+        }
+    }
+
+    #[test]
+    fn fails_without_balance_of() {
+        // STOP, every call returns nothing.
+        let mut simulator = simulator(Bytes::from_static(&[0x00]));
+        assert!(find_balance_slot(&mut simulator, TOKEN).is_err());
+    }
+}
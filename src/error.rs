@@ -0,0 +1,81 @@
+use alloy::{
+    primitives::Bytes,
+    sol_types::{SolError, decode_revert_reason},
+};
+use revm::context::result::{EVMError, HaltReason};
+
+pub type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SimError {
+    /// Call reverted, `reason` is set for `Error(string)`,
+    /// `Panic(uint256)` and raw string payloads.
+    #[error("execution reverted: {}", .reason.as_deref().unwrap_or("<custom error>"))]
+    Revert {
+        output: Bytes,
+        reason: Option<String>,
+        gas_used: u64,
+    },
+
+    #[error("execution halted: {reason:?}")]
+    Halt { reason: HaltReason, gas_used: u64 },
+
+    /// Call was expected to revert but succeeded.
+    #[error("unexpected success")]
+    UnexpectedSuccess { output: Bytes, gas_used: u64 },
+
+    /// Failed to fetch the state (usually an RPC failure in `AlloyDB`).
+    #[error("database error: {0}")]
+    Database(BoxedError),
+
+    /// Transaction or block environment rejected by the EVM.
+    #[error("invalid environment: {0}")]
+    Evm(String),
+
+    /// Revert payload is not the one the contract is expected to revert with.
+    #[error("unexpected revert: {reason}")]
+    UnexpectedRevert { output: Bytes, reason: String },
+
+    #[error("abi decode error: {0}")]
+    Abi(#[from] alloy::sol_types::Error),
+}
+
+impl SimError {
+    pub fn revert(output: Bytes, gas_used: u64) -> Self {
+        let reason = decode_revert_reason(&output);
+        Self::Revert {
+            output,
+            reason,
+            gas_used,
+        }
+    }
+
+    /// Decodes a custom error from the revert payload.
+    pub fn custom_error<E: SolError>(&self) -> Option<E> {
+        match self {
+            Self::Revert { output, .. } => E::abi_decode(output).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn is_revert(&self) -> bool {
+        matches!(self, Self::Revert { .. })
+    }
+
+    /// True for errors not caused by the simulated transaction itself.
+    pub fn is_database(&self) -> bool {
+        matches!(self, Self::Database(_))
+    }
+}
+
+impl<E> From<EVMError<E>> for SimError
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn from(error: EVMError<E>) -> Self {
+        match error {
+            EVMError::Database(e) => Self::Database(Box::new(e)),
+            error => Self::Evm(error.to_string()),
+        }
+    }
+}
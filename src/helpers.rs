@@ -1,6 +1,33 @@
 use alloy::primitives::U256;
+use anyhow::ensure;
+
+/// `count` volumes `from + step * i` for `i` in `1..=count`, with
+/// `step = (to - from) / count`, largest first.
+pub fn volumes(from: U256, to: U256, count: usize) -> anyhow::Result<Vec<U256>> {
+    ensure!(count >= 1, "at least one volume is needed");
+    ensure!(from < to, "volume range is empty: {from}..{to}");
 
-pub fn volumes(from: U256, to: U256, count: usize) -> Vec<U256> {
     let step = (to - from) / U256::from(count);
-    (1..=count).map(|i| step * U256::from(i)).rev().collect()
+    Ok((1..=count)
+        .map(|i| from + step * U256::from(i))
+        .rev()
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volumes_start_after_from() {
+        let volumes = volumes(U256::from(100), U256::from(200), 4).unwrap();
+        assert_eq!(volumes, [200, 175, 150, 125].map(U256::from));
+    }
+
+    #[test]
+    fn volumes_reject_empty_ranges() {
+        assert!(volumes(U256::ZERO, U256::from(10), 0).is_err());
+        assert!(volumes(U256::from(10), U256::from(10), 1).is_err());
+        assert!(volumes(U256::from(10), U256::ZERO, 1).is_err());
+    }
 }
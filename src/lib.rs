@@ -3,9 +3,24 @@ use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitEx
 pub mod abi;
 pub mod account;
 pub mod address;
+pub mod bench;
+pub mod bytecode;
+pub mod cache;
 pub mod constant;
+pub mod erc20;
+pub mod error;
 pub mod helpers;
+pub mod optimize;
+pub mod path;
+pub mod quote;
 pub mod revm;
+pub mod rpc;
+pub mod uni_v2;
+pub mod uni_v3;
+pub mod verify;
+
+#[cfg(test)]
+mod test_support;
 
 pub fn setup_tracing() {
     tracing_subscriber::registry()
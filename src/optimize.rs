@@ -0,0 +1,214 @@
+//! Search for the input that maximises the profit of a path.
+
+use alloy::primitives::{I256, U256};
+
+// 1 / golden ratio, scaled by `GOLDEN_SCALE`.
+const GOLDEN_RATIO_INV: u64 = 618_034;
+const GOLDEN_SCALE: u64 = 1_000_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Optimum {
+    pub amount_in: U256,
+    pub profit: I256,
+    /// Number of times `profit` was evaluated.
+    pub simulations: usize,
+}
+
+/// Finds the input in `(0, max]` that maximises a concave `profit` curve.
+///
+/// The bracketing phase starts at `initial` and doubles the input while the
+/// profit keeps growing, then a golden-section search narrows the bracket
+/// down to `tolerance` wei. Returns the best input seen, even if its profit
+/// is not positive.
+///
+/// The profit may be negative for small inputs, e.g. once a fixed gas cost
+/// is deducted, so no input is evaluated at zero.
+pub fn maximize_profit<F>(
+    mut profit: F,
+    initial: U256,
+    max: U256,
+    tolerance: U256,
+) -> anyhow::Result<Optimum>
+where
+    F: FnMut(U256) -> anyhow::Result<I256>,
+{
+    let mut search = Search {
+        profit: &mut profit,
+        best: Optimum {
+            amount_in: U256::ZERO,
+            profit: I256::ZERO,
+            simulations: 0,
+        },
+    };
+
+    // Bracketing: find `a < b < c` with the peak in `[a, c]`.
+    let mut a = U256::ZERO;
+    let mut b = initial.clamp(U256::from(1), max);
+    let mut fb = search.eval(b)?;
+    let mut c = b;
+    while b < max {
+        c = b.saturating_mul(U256::from(2)).min(max);
+        let fc = search.eval(c)?;
+        if fc <= fb {
+            break;
+        }
+        a = b;
+        b = c;
+        fb = fc;
+    }
+
+    // Golden-section search on `[a, c]`.
+    let golden =
+        |a: U256, c: U256| (c - a) * U256::from(GOLDEN_RATIO_INV) / U256::from(GOLDEN_SCALE);
+    let mut x1 = (c - golden(a, c)).max(U256::from(1));
+    let mut x2 = (a + golden(a, c)).max(U256::from(1));
+    let mut f1 = search.eval(x1)?;
+    let mut f2 = search.eval(x2)?;
+
+    while c - a > tolerance && x1 < x2 {
+        if f1 < f2 {
+            a = x1;
+            x1 = x2;
+            f1 = f2;
+            x2 = a + golden(a, c);
+            f2 = search.eval(x2)?;
+        } else {
+            c = x2;
+            x2 = x1;
+            f2 = f1;
+            x1 = (c - golden(a, c)).max(U256::from(1));
+            f1 = search.eval(x1)?;
+        }
+    }
+
+    Ok(search.best)
+}
+
+struct Search<'a, F> {
+    profit: &'a mut F,
+    best: Optimum,
+}
+
+impl<F> Search<'_, F>
+where
+    F: FnMut(U256) -> anyhow::Result<I256>,
+{
+    fn eval(&mut self, amount_in: U256) -> anyhow::Result<I256> {
+        let profit = (self.profit)(amount_in)?;
+        self.best.simulations += 1;
+        if self.best.amount_in.is_zero() || profit > self.best.profit {
+            self.best.amount_in = amount_in;
+            self.best.profit = profit;
+        }
+        Ok(profit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ETH: u128 = 1_000_000_000_000_000_000;
+
+    fn eth(amount: u128) -> U256 {
+        U256::from(amount * ETH)
+    }
+
+    fn signed(value: U256) -> I256 {
+        I256::try_from(value).unwrap()
+    }
+
+    /// `height - (x - peak)² / 1e20`, concave with its maximum at `peak`.
+    fn parabola(peak: U256, height: U256) -> impl FnMut(U256) -> anyhow::Result<I256> {
+        move |x| {
+            let distance = x.abs_diff(peak);
+            Ok(signed(height) - signed(distance * distance / U256::from(10).pow(U256::from(20))))
+        }
+    }
+
+    #[test]
+    fn finds_interior_peak() {
+        let peak = eth(3) + U256::from(123_456_789_u64);
+        let optimum = maximize_profit(
+            parabola(peak, eth(1) / U256::from(10)),
+            eth(1) / U256::from(100),
+            eth(100),
+            U256::from(1_000_000_000),
+        )
+        .unwrap();
+
+        assert!(optimum.amount_in.abs_diff(peak) < U256::from(10).pow(U256::from(12)));
+        assert_eq!(optimum.profit, signed(eth(1) / U256::from(10)));
+    }
+
+    #[test]
+    fn stops_at_max_when_peak_is_beyond() {
+        let optimum = maximize_profit(
+            parabola(eth(500), eth(3000)),
+            eth(1) / U256::from(100),
+            eth(100),
+            U256::from(1_000_000_000),
+        )
+        .unwrap();
+
+        assert_eq!(optimum.amount_in, eth(100));
+    }
+
+    #[test]
+    fn returns_best_loss_when_always_negative() {
+        let optimum = maximize_profit(
+            |x| Ok(-signed(x) - signed(eth(1))),
+            eth(1) / U256::from(100),
+            eth(100),
+            U256::from(1_000_000_000),
+        )
+        .unwrap();
+
+        assert!(optimum.profit < I256::ZERO);
+        assert!(optimum.amount_in <= eth(1) / U256::from(100));
+        assert_eq!(optimum.profit, -signed(optimum.amount_in) - signed(eth(1)));
+    }
+
+    #[test]
+    fn brackets_past_a_negative_start() {
+        // 0.02x - 0.002x² in ETH, minus 0.01 ETH of gas: -0.0098 ETH at the
+        // initial 0.01 ETH, +0.04 ETH at the 5 ETH optimum.
+        let gas = signed(eth(1) / U256::from(100));
+        let net_profit = |x: U256| {
+            let gross = signed(x * U256::from(2) / U256::from(100))
+                - signed(x * x * U256::from(2) / U256::from(1000) / eth(1));
+            Ok(gross - gas)
+        };
+        let optimum = maximize_profit(
+            net_profit,
+            eth(1) / U256::from(100),
+            eth(100),
+            U256::from(1_000_000_000),
+        )
+        .unwrap();
+
+        assert!(optimum.amount_in.abs_diff(eth(5)) < U256::from(10).pow(U256::from(12)));
+        assert_eq!(optimum.profit, signed(eth(4) / U256::from(100)));
+    }
+
+    #[test]
+    fn stops_once_within_tolerance() {
+        let peak = eth(3);
+        let simulations = |tolerance: U256| {
+            maximize_profit(
+                parabola(peak, eth(1)),
+                eth(1) / U256::from(100),
+                eth(100),
+                tolerance,
+            )
+            .unwrap()
+            .simulations
+        };
+
+        let coarse = simulations(eth(1) / U256::from(10));
+        let fine = simulations(U256::from(1_000_000_000));
+        assert!(coarse < fine);
+        // Each golden-section step shrinks the bracket by ~0.618.
+        assert!(fine < 80, "{fine} simulations");
+    }
+}
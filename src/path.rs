@@ -0,0 +1,662 @@
+//! Arbitrage cycles through any number of pools, evaluated via revm or native math.
+
+use alloy::primitives::{Address, I256, U256};
+use anyhow::{anyhow, ensure};
+use revm::{
+    Database, DatabaseCommit,
+    context::{BlockEnv, result::ExecutionResult},
+};
+
+use crate::{
+    abi::{
+        decode_get_reserves_response, decode_quoter_revert, get_amount_out_calldata,
+        get_reserves_calldata,
+    },
+    address::CUSTOM_QUOTER_ADDR,
+    error::SimError,
+    revm::Simulator,
+    uni_v2::UniV2Pair,
+    uni_v3::UniV3Pool,
+};
+
+/// Intrinsic gas of a transaction, paid once per path.
+pub const TX_BASE_GAS: u64 = 21_000;
+/// Rough gas of a Uniswap V2 pair swap.
+pub const V2_SWAP_GAS: u64 = 60_000;
+/// Rough gas of a Uniswap V3 pool swap that doesn't cross any tick.
+pub const V3_SWAP_GAS: u64 = 90_000;
+/// Rough extra gas for every initialized tick crossed by a V3 swap.
+pub const V3_TICK_CROSS_GAS: u64 = 25_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    UniV3 { fee: u32 },
+    UniV2 { fee_numerator: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hop {
+    pub pool: Address,
+    pub protocol: Protocol,
+    pub token_in: Address,
+    pub token_out: Address,
+}
+
+impl Hop {
+    pub fn new(pool: Address, protocol: Protocol, token_in: Address, token_out: Address) -> Self {
+        Self {
+            pool,
+            protocol,
+            token_in,
+            token_out,
+        }
+    }
+
+    pub fn zero_for_one(&self) -> bool {
+        self.token_in < self.token_out
+    }
+}
+
+/// A cycle of hops that starts and ends with the same token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path {
+    hops: Vec<Hop>,
+}
+
+/// Native state of a pool, loaded once and used to quote without the EVM.
+#[derive(Debug, Clone)]
+pub enum PoolState {
+    V3(UniV3Pool),
+    V2(UniV2Pair),
+}
+
+impl PoolState {
+    pub fn address(&self) -> Address {
+        match self {
+            PoolState::V3(pool) => pool.address,
+            PoolState::V2(pair) => pair.address,
+        }
+    }
+}
+
+/// How to evaluate a path.
+pub enum Backend<'a, DB: Database> {
+    /// Quotes every V3 hop through the custom quoter deployed at
+    /// [`CUSTOM_QUOTER_ADDR`] and every V2 hop from the `getReserves` response.
+    Revm(&'a mut Simulator<DB>),
+    /// Quotes every hop with the native math, pools as returned by [`Path::load_pools`].
+    Native(&'a [PoolState]),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HopResult {
+    pub hop: Hop,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// Gas of the hop itself, without the intrinsic transaction gas.
+    pub gas_used: u64,
+    /// `gas_used` is a rough constant (e.g. [`V2_SWAP_GAS`]) rather than measured by revm.
+    pub gas_estimated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathResult {
+    pub hops: Vec<HopResult>,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    /// Gas of all the hops plus [`TX_BASE_GAS`].
+    pub gas_used: u64,
+    /// `amount_out - amount_in` in the start token, gas is not deducted.
+    pub profit: I256,
+}
+
+/// Prices gas in wei, so in WETH when the path starts with WETH.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasPricing {
+    pub base_fee: u128,
+    pub priority_fee: u128,
+    /// Part of the profit left after gas paid to the block builder, in percent.
+    pub builder_tip_percent: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetProfit {
+    pub gas_cost: U256,
+    pub builder_tip: U256,
+    /// Profit after gas and the builder tip.
+    pub net_profit: I256,
+}
+
+impl GasPricing {
+    /// Uses the base fee of the (pinned) block.
+    pub fn new(block: &BlockEnv, priority_fee: u128) -> Self {
+        Self {
+            base_fee: block.basefee as u128,
+            priority_fee,
+            builder_tip_percent: 0,
+        }
+    }
+
+    pub fn with_builder_tip_percent(mut self, builder_tip_percent: u8) -> Self {
+        self.builder_tip_percent = builder_tip_percent.min(100);
+        self
+    }
+
+    pub fn gas_price(&self) -> U256 {
+        U256::from(self.base_fee) + U256::from(self.priority_fee)
+    }
+}
+
+impl PathResult {
+    /// Part of `gas_used` that is estimated rather than measured, see [`HopResult::gas_estimated`].
+    pub fn estimated_gas(&self) -> u64 {
+        self.hops
+            .iter()
+            .filter(|hop| hop.gas_estimated)
+            .map(|hop| hop.gas_used)
+            .sum()
+    }
+
+    /// Deducts the gas cost and the builder tip from the profit.
+    pub fn net_profit(&self, pricing: &GasPricing) -> anyhow::Result<NetProfit> {
+        let gas_cost = U256::from(self.gas_used) * pricing.gas_price();
+        let after_gas = self.profit - I256::try_from(gas_cost)?;
+        let builder_tip = if after_gas > I256::ZERO {
+            after_gas.into_raw() * U256::from(pricing.builder_tip_percent) / U256::from(100)
+        } else {
+            U256::ZERO
+        };
+        Ok(NetProfit {
+            gas_cost,
+            builder_tip,
+            net_profit: after_gas - I256::try_from(builder_tip)?,
+        })
+    }
+}
+
+impl Path {
+    pub fn new(hops: Vec<Hop>) -> anyhow::Result<Self> {
+        let (first, last) = match (hops.first(), hops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Err(anyhow!("path has no hops")),
+        };
+        for hop in &hops {
+            ensure!(
+                hop.token_in != hop.token_out,
+                "hop through {} swaps {} for itself",
+                hop.pool,
+                hop.token_in
+            );
+        }
+        for pair in hops.windows(2) {
+            ensure!(
+                pair[0].token_out == pair[1].token_in,
+                "hop through {} ends with {} but the next one starts with {}",
+                pair[0].pool,
+                pair[0].token_out,
+                pair[1].token_in
+            );
+        }
+        ensure!(
+            last.token_out == first.token_in,
+            "path starts with {} but ends with {}",
+            first.token_in,
+            last.token_out
+        );
+        Ok(Self { hops })
+    }
+
+    pub fn hops(&self) -> &[Hop] {
+        &self.hops
+    }
+
+    /// The token the cycle starts and ends with.
+    pub fn token(&self) -> Address {
+        self.hops[0].token_in
+    }
+
+    /// Loads the native state of every pool, see [`UniV3Pool::load`] for `word_radius`.
+    pub fn load_pools<DB>(&self, db: &mut DB, word_radius: i16) -> anyhow::Result<Vec<PoolState>>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        self.hops
+            .iter()
+            .map(|hop| {
+                let pool = match hop.protocol {
+                    Protocol::UniV3 { fee } => {
+                        PoolState::V3(UniV3Pool::load(db, hop.pool, fee, word_radius)?)
+                    }
+                    Protocol::UniV2 { fee_numerator } => {
+                        PoolState::V2(UniV2Pair::load(db, hop.pool, fee_numerator)?)
+                    }
+                };
+                Ok(pool)
+            })
+            .collect()
+    }
+
+    /// Swaps `amount_in` through every hop.
+    pub fn evaluate<DB>(
+        &self,
+        backend: Backend<'_, DB>,
+        amount_in: U256,
+    ) -> anyhow::Result<PathResult>
+    where
+        DB: Database + DatabaseCommit,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let mut backend = backend;
+        let mut hops = Vec::with_capacity(self.hops.len());
+        let mut amount = amount_in;
+
+        for (index, hop) in self.hops.iter().enumerate() {
+            let (amount_out, gas_used, gas_estimated) = match &mut backend {
+                Backend::Revm(simulator) => quote_revm(simulator, hop, amount)?,
+                Backend::Native(pools) => {
+                    let pool = pools
+                        .get(index)
+                        .ok_or_else(|| anyhow!("no native state for hop {index}"))?;
+                    quote_native(pool, hop, amount)?
+                }
+            };
+            hops.push(HopResult {
+                hop: *hop,
+                amount_in: amount,
+                amount_out,
+                gas_used,
+                gas_estimated,
+            });
+            amount = amount_out;
+        }
+
+        let gas_used = TX_BASE_GAS + hops.iter().map(|hop| hop.gas_used).sum::<u64>();
+        let profit = I256::try_from(amount)? - I256::try_from(amount_in)?;
+
+        Ok(PathResult {
+            hops,
+            amount_in,
+            amount_out: amount,
+            gas_used,
+            profit,
+        })
+    }
+}
+
+/// Returns the amount out, the gas of the hop and whether the gas is estimated.
+///
+/// V3 gas is measured by the quoter swap. V2 amounts are computed from the
+/// `getReserves` response, so their gas is the [`V2_SWAP_GAS`] estimate.
+fn quote_revm<DB>(
+    simulator: &mut Simulator<DB>,
+    hop: &Hop,
+    amount_in: U256,
+) -> anyhow::Result<(U256, u64, bool)>
+where
+    DB: Database + DatabaseCommit,
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
+    match hop.protocol {
+        Protocol::UniV3 { .. } => {
+            let calldata =
+                get_amount_out_calldata(hop.pool, hop.token_in, hop.token_out, amount_in);
+            // The quoter always reverts with the swap deltas.
+            match simulator.transact(CUSTOM_QUOTER_ADDR, calldata)? {
+                ExecutionResult::Revert { output, gas_used } => {
+                    let deltas = decode_quoter_revert(&output)?;
+                    ensure!(
+                        deltas.zero_for_one() == hop.zero_for_one(),
+                        "quoter swapped {} in the wrong direction: {deltas:?}",
+                        hop.pool
+                    );
+                    Ok((
+                        deltas.amount_out(),
+                        gas_used.saturating_sub(TX_BASE_GAS),
+                        false,
+                    ))
+                }
+                ExecutionResult::Success {
+                    output, gas_used, ..
+                } => Err(SimError::UnexpectedSuccess {
+                    output: output.into_data(),
+                    gas_used,
+                }
+                .into()),
+                ExecutionResult::Halt { reason, gas_used } => {
+                    Err(SimError::Halt { reason, gas_used }.into())
+                }
+            }
+        }
+        Protocol::UniV2 { fee_numerator } => {
+            let response = simulator.call(hop.pool, get_reserves_calldata())?;
+            let (reserve0, reserve1) = decode_get_reserves_response(response)?;
+            let pair = UniV2Pair::new(hop.pool, reserve0, reserve1, fee_numerator);
+            Ok((
+                pair.exact_input(hop.zero_for_one(), amount_in)?,
+                V2_SWAP_GAS,
+                true,
+            ))
+        }
+    }
+}
+
+/// Same as [`quote_revm`], the gas of native quotes is always estimated.
+fn quote_native(pool: &PoolState, hop: &Hop, amount_in: U256) -> anyhow::Result<(U256, u64, bool)> {
+    ensure!(
+        pool.address() == hop.pool,
+        "native state of {} used for {}",
+        pool.address(),
+        hop.pool
+    );
+    match pool {
+        PoolState::V3(pool) => {
+            let zero_for_one = hop.zero_for_one();
+            let result = pool.swap(zero_for_one, I256::try_from(amount_in)?, None)?;
+            let amount_out = if zero_for_one {
+                result.amount1
+            } else {
+                result.amount0
+            };
+            let gas_used = V3_SWAP_GAS + V3_TICK_CROSS_GAS * result.ticks_crossed as u64;
+            Ok((amount_out.unsigned_abs(), gas_used, true))
+        }
+        PoolState::V2(pair) => Ok((
+            pair.exact_input(hop.zero_for_one(), amount_in)?,
+            V2_SWAP_GAS,
+            true,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use revm::database::{CacheDB, EmptyDB};
+
+    use alloy::primitives::Bytes;
+
+    use super::*;
+    use crate::{
+        test_support::{Asm, insert_code, op::*},
+        uni_v2::UNI_V2_FEE_NUMERATOR,
+        uni_v3::pool::tick_spacing,
+    };
+
+    const A: Address = Address::repeat_byte(0x0a);
+    const B: Address = Address::repeat_byte(0x0b);
+    const C: Address = Address::repeat_byte(0x0c);
+
+    const V2_FEE: Protocol = Protocol::UniV2 {
+        fee_numerator: UNI_V2_FEE_NUMERATOR,
+    };
+
+    fn pair(address: u8, reserve0: u64, reserve1: u64) -> PoolState {
+        PoolState::V2(UniV2Pair::new(
+            Address::repeat_byte(address),
+            U256::from(reserve0),
+            U256::from(reserve1),
+            UNI_V2_FEE_NUMERATOR,
+        ))
+    }
+
+    /// 0.3% pool at price 1 without any initialized tick around it.
+    fn v3_pool(address: u8) -> UniV3Pool {
+        UniV3Pool {
+            address: Address::repeat_byte(address),
+            fee: 3000,
+            tick_spacing: tick_spacing(3000).unwrap(),
+            sqrt_price_x96: U256::from(1) << 96,
+            tick: 0,
+            liquidity: 1_000_000_000_000_000_000,
+            tick_bitmap: HashMap::from([(-1, U256::ZERO), (0, U256::ZERO)]),
+            ticks: HashMap::new(),
+        }
+    }
+
+    fn evaluate(path: &Path, pools: &[PoolState], amount_in: u64) -> PathResult {
+        path.evaluate(
+            Backend::<CacheDB<EmptyDB>>::Native(pools),
+            U256::from(amount_in),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn rejects_broken_paths() {
+        let first = Hop::new(Address::repeat_byte(1), V2_FEE, A, B);
+
+        assert!(Path::new(Vec::new()).is_err());
+        // The second hop doesn't start with the token the first one ends with.
+        let broken = Hop::new(Address::repeat_byte(2), V2_FEE, C, A);
+        assert!(Path::new(vec![first, broken]).is_err());
+        // Not a cycle.
+        let open = Hop::new(Address::repeat_byte(2), V2_FEE, B, C);
+        assert!(Path::new(vec![first, open]).is_err());
+        // A single hop back to the same token.
+        let itself = Hop::new(Address::repeat_byte(1), V2_FEE, A, A);
+        assert!(Path::new(vec![itself]).is_err());
+
+        let back = Hop::new(Address::repeat_byte(2), V2_FEE, B, A);
+        let path = Path::new(vec![first, back]).unwrap();
+        assert_eq!(path.token(), A);
+    }
+
+    #[test]
+    fn chains_v2_amounts() {
+        let path = Path::new(vec![
+            Hop::new(Address::repeat_byte(1), V2_FEE, A, B),
+            Hop::new(Address::repeat_byte(2), V2_FEE, B, A),
+        ])
+        .unwrap();
+        // A is token0 of both pairs, B is twice as cheap on the first one.
+        let pools = [pair(1, 10_000, 20_000), pair(2, 10_000, 10_000)];
+
+        let result = evaluate(&path, &pools, 1_000);
+
+        // 1000 * 9970 * 20000 / (10000 * 10000 + 1000 * 9970)
+        assert_eq!(result.hops[0].amount_out, U256::from(1_813));
+        // 1813 * 9970 * 10000 / (10000 * 10000 + 1813 * 9970)
+        assert_eq!(result.hops[1].amount_in, U256::from(1_813));
+        assert_eq!(result.hops[1].amount_out, U256::from(1_530));
+        assert_eq!(result.amount_out, U256::from(1_530));
+        assert_eq!(result.profit, I256::try_from(530).unwrap());
+        assert_eq!(result.gas_used, TX_BASE_GAS + 2 * V2_SWAP_GAS);
+        assert_eq!(result.estimated_gas(), 2 * V2_SWAP_GAS);
+    }
+
+    /// `getReserves` of a pair with constant reserves.
+    fn pair_code(reserve0: u16, reserve1: u16) -> Bytes {
+        let mut asm = Asm::default();
+        asm.push(reserve0)
+            .ops(&[PUSH1, 0x00, MSTORE])
+            .push(reserve1)
+            .ops(&[PUSH1, 0x20, MSTORE, PUSH1, 0x60, PUSH1, 0x00, RETURN]);
+        asm.finish()
+    }
+
+    #[test]
+    fn labels_revm_v2_gas_as_estimated() {
+        let path = Path::new(vec![
+            Hop::new(Address::repeat_byte(1), V2_FEE, A, B),
+            Hop::new(Address::repeat_byte(2), V2_FEE, B, A),
+        ])
+        .unwrap();
+        let mut db = CacheDB::new(EmptyDB::default());
+        insert_code(&mut db, Address::repeat_byte(1), pair_code(10_000, 20_000));
+        insert_code(&mut db, Address::repeat_byte(2), pair_code(10_000, 10_000));
+        let mut simulator = Simulator::new(db);
+
+        let result = path
+            .evaluate(Backend::Revm(&mut simulator), U256::from(1_000))
+            .unwrap();
+
+        assert_eq!(result.amount_out, U256::from(1_530));
+        assert!(result.hops.iter().all(|hop| hop.gas_estimated));
+        assert_eq!(result.estimated_gas(), 2 * V2_SWAP_GAS);
+    }
+
+    #[test]
+    fn chains_v2_and_v3_amounts() {
+        let path = Path::new(vec![
+            Hop::new(Address::repeat_byte(1), V2_FEE, A, B),
+            Hop::new(Address::repeat_byte(3), Protocol::UniV3 { fee: 3000 }, B, C),
+            Hop::new(Address::repeat_byte(2), V2_FEE, C, A),
+        ])
+        .unwrap();
+        let pool = v3_pool(3);
+        let pools = [
+            pair(1, 10_000, 20_000),
+            PoolState::V3(pool.clone()),
+            pair(2, 10_000, 10_000),
+        ];
+
+        let result = evaluate(&path, &pools, 1_000);
+
+        let v3_out = pool.exact_input(true, U256::from(1_813)).unwrap();
+        // No price impact with this much liquidity: 1813 minus the 0.3% fee
+        // rounded up, then the output rounded down.
+        assert_eq!(v3_out, U256::from(1_806));
+        let v2_out = UniV2Pair::new(
+            Address::ZERO,
+            U256::from(10_000),
+            U256::from(10_000),
+            UNI_V2_FEE_NUMERATOR,
+        )
+        .exact_input(false, v3_out)
+        .unwrap();
+
+        let amounts: Vec<_> = result
+            .hops
+            .iter()
+            .map(|hop| (hop.amount_in, hop.amount_out))
+            .collect();
+        assert_eq!(
+            amounts,
+            [
+                (U256::from(1_000), U256::from(1_813)),
+                (U256::from(1_813), v3_out),
+                (v3_out, v2_out),
+            ]
+        );
+        assert_eq!(result.amount_out, v2_out);
+        assert_eq!(result.gas_used, TX_BASE_GAS + 2 * V2_SWAP_GAS + V3_SWAP_GAS);
+    }
+
+    #[test]
+    fn rejects_pools_of_other_hops() {
+        let path = Path::new(vec![
+            Hop::new(Address::repeat_byte(1), V2_FEE, A, B),
+            Hop::new(Address::repeat_byte(2), V2_FEE, B, A),
+        ])
+        .unwrap();
+        let pools = [pair(2, 10_000, 10_000), pair(1, 10_000, 20_000)];
+        let result = path.evaluate(
+            Backend::<CacheDB<EmptyDB>>::Native(&pools),
+            U256::from(1_000),
+        );
+        assert!(result.is_err());
+        assert!(
+            path.evaluate(
+                Backend::<CacheDB<EmptyDB>>::Native(&pools[..1]),
+                U256::from(1_000)
+            )
+            .is_err()
+        );
+    }
+
+    const GWEI: u128 = 1_000_000_000;
+
+    fn path_result(profit: i128, gas_used: u64) -> PathResult {
+        PathResult {
+            hops: Vec::new(),
+            amount_in: U256::ZERO,
+            amount_out: U256::ZERO,
+            gas_used,
+            profit: I256::try_from(profit).unwrap(),
+        }
+    }
+
+    #[test]
+    fn deducts_gas_at_base_plus_priority_fee() {
+        let block = BlockEnv {
+            basefee: 10 * GWEI as u64,
+            ..Default::default()
+        };
+        let pricing = GasPricing::new(&block, 2 * GWEI);
+        assert_eq!(pricing.gas_price(), U256::from(12 * GWEI));
+
+        let net = path_result(10_000_000 * GWEI as i128, 100_000)
+            .net_profit(&pricing)
+            .unwrap();
+        assert_eq!(net.gas_cost, U256::from(100_000 * 12 * GWEI));
+        assert_eq!(net.builder_tip, U256::ZERO);
+        assert_eq!(
+            net.net_profit,
+            I256::try_from(8_800_000 * GWEI as i128).unwrap()
+        );
+    }
+
+    #[test]
+    fn tips_a_share_of_the_profit_after_gas() {
+        let pricing = GasPricing {
+            base_fee: 10 * GWEI,
+            priority_fee: 2 * GWEI,
+            builder_tip_percent: 0,
+        }
+        .with_builder_tip_percent(25);
+
+        let net = path_result(10_000_000 * GWEI as i128, 100_000)
+            .net_profit(&pricing)
+            .unwrap();
+        // A quarter of the 8.8e15 left after gas.
+        assert_eq!(net.builder_tip, U256::from(2_200_000 * GWEI));
+        assert_eq!(
+            net.net_profit,
+            I256::try_from(6_600_000 * GWEI as i128).unwrap()
+        );
+
+        // The whole profit at most.
+        let pricing = pricing.with_builder_tip_percent(150);
+        assert_eq!(pricing.builder_tip_percent, 100);
+        let net = path_result(10_000_000 * GWEI as i128, 100_000)
+            .net_profit(&pricing)
+            .unwrap();
+        assert_eq!(net.net_profit, I256::ZERO);
+    }
+
+    #[test]
+    fn never_tips_without_profit() {
+        let pricing = GasPricing {
+            base_fee: 10 * GWEI,
+            priority_fee: 0,
+            builder_tip_percent: 50,
+        };
+
+        // Gross profit below the gas cost.
+        let net = path_result(500_000 * GWEI as i128, 100_000)
+            .net_profit(&pricing)
+            .unwrap();
+        assert_eq!(net.builder_tip, U256::ZERO);
+        assert_eq!(
+            net.net_profit,
+            I256::try_from(-500_000 * GWEI as i128).unwrap()
+        );
+
+        // Gross profit equal to the gas cost.
+        let net = path_result(1_000_000 * GWEI as i128, 100_000)
+            .net_profit(&pricing)
+            .unwrap();
+        assert_eq!(net.builder_tip, U256::ZERO);
+        assert_eq!(net.net_profit, I256::ZERO);
+
+        // Losing trade, gas only makes it worse.
+        let net = path_result(-1_000, 100_000).net_profit(&pricing).unwrap();
+        assert_eq!(net.builder_tip, U256::ZERO);
+        assert_eq!(
+            net.net_profit,
+            I256::try_from(-1_000 - 1_000_000 * GWEI as i128).unwrap()
+        );
+    }
+}
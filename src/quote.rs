@@ -0,0 +1,450 @@
+//! The same Quoter V2 exact-input quote behind `eth_call`, Anvil, revm and the native math.
+
+use std::{cell::RefCell, future::Future, ops::Div, sync::Arc};
+
+use alloy::{
+    eips::BlockId,
+    network::TransactionBuilder,
+    node_bindings::{Anvil, AnvilInstance},
+    primitives::{Address, Bytes, I256, U160, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::TransactionRequest,
+    transports::http::reqwest::Url,
+};
+use anyhow::{anyhow, ensure};
+use revm::Database;
+use tokio::task::JoinSet;
+
+use crate::{
+    abi::{
+        QuoteResult, decode_get_pool_response, decode_quote_response_with_limit,
+        default_sqrt_price_limit_x96, get_pool_calldata, quote_calldata_with_limit,
+    },
+    address::{ME, V3_FACTORY_ADDR, V3_QUOTER_ADDR},
+    bytecode::BytecodeRegistry,
+    cache::EvmCache,
+    erc20::set_erc20_balance,
+    revm::{
+        RevmProvider, Simulator, fetch_block_env, init_account, init_account_with_bytecode,
+        init_cache_db, init_proxy_implementation,
+    },
+    uni_v3::UniV3Pool,
+};
+
+/// Single-pool exact-input swap to quote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuoteRequest {
+    pub token_in: Address,
+    pub token_out: Address,
+    pub fee: u32,
+    pub amount_in: U256,
+    /// MIN/MAX price (depending on direction) if not set.
+    pub sqrt_price_limit_x96: Option<U160>,
+}
+
+impl QuoteRequest {
+    pub fn new(token_in: Address, token_out: Address, fee: u32, amount_in: U256) -> Self {
+        Self {
+            token_in,
+            token_out,
+            fee,
+            amount_in,
+            sqrt_price_limit_x96: None,
+        }
+    }
+
+    pub fn with_sqrt_price_limit_x96(mut self, sqrt_price_limit_x96: Option<U160>) -> Self {
+        self.sqrt_price_limit_x96 = sqrt_price_limit_x96;
+        self
+    }
+
+    pub fn zero_for_one(&self) -> bool {
+        self.token_in < self.token_out
+    }
+
+    /// Quoter V2 `quoteExactInputSingle` calldata.
+    pub fn calldata(&self) -> Bytes {
+        quote_calldata_with_limit(
+            self.token_in,
+            self.token_out,
+            self.amount_in,
+            self.fee,
+            self.sqrt_price_limit_x96,
+        )
+    }
+
+    pub fn decode(&self, response: Bytes) -> anyhow::Result<QuoteResult> {
+        Ok(decode_quote_response_with_limit(
+            response,
+            self.sqrt_price_limit_x96,
+        )?)
+    }
+}
+
+/// Something that quotes swaps at a fixed block.
+///
+/// The futures are not `Send`, the revm backends hold a `Simulator`.
+pub trait QuoteBackend {
+    fn quote(&self, request: QuoteRequest) -> impl Future<Output = anyhow::Result<QuoteResult>>;
+
+    /// Quotes one request after another, results are in the order of the requests.
+    fn quote_batch(
+        &self,
+        requests: Vec<QuoteRequest>,
+    ) -> impl Future<Output = anyhow::Result<Vec<QuoteResult>>> {
+        async move {
+            let mut quotes = Vec::with_capacity(requests.len());
+            for request in requests {
+                quotes.push(self.quote(request).await?);
+            }
+            Ok(quotes)
+        }
+    }
+}
+
+/// Uniswap V3 pool of a token pair and fee tier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolKey {
+    pub address: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub fee: u32,
+}
+
+impl PoolKey {
+    pub fn new(address: Address, token_a: Address, token_b: Address, fee: u32) -> Self {
+        let (token0, token1) = if token_a < token_b {
+            (token_a, token_b)
+        } else {
+            (token_b, token_a)
+        };
+        Self {
+            address,
+            token0,
+            token1,
+            fee,
+        }
+    }
+
+    /// Looks the pool up in the factory.
+    pub async fn find(
+        provider: &RevmProvider,
+        block: u64,
+        token_a: Address,
+        token_b: Address,
+        fee: u32,
+    ) -> anyhow::Result<Self> {
+        let tx = TransactionRequest::default()
+            .to(V3_FACTORY_ADDR)
+            .with_input(get_pool_calldata(token_a, token_b, fee));
+        let response = provider.call(tx).block(BlockId::number(block)).await?;
+        let address = decode_get_pool_response(response)?;
+        ensure!(
+            !address.is_zero(),
+            "no pool for {token_a} / {token_b} with fee {fee}"
+        );
+        Ok(Self::new(address, token_a, token_b, fee))
+    }
+
+    fn matches(&self, request: &QuoteRequest) -> bool {
+        *self
+            == Self::new(
+                self.address,
+                request.token_in,
+                request.token_out,
+                request.fee,
+            )
+    }
+}
+
+/// `eth_call` to the Quoter V2 on the node.
+#[derive(Clone)]
+pub struct EthCallBackend {
+    provider: RevmProvider,
+    block: u64,
+    quoter: Address,
+}
+
+impl EthCallBackend {
+    pub fn new(provider: RevmProvider, block: u64) -> Self {
+        Self {
+            provider,
+            block,
+            quoter: V3_QUOTER_ADDR,
+        }
+    }
+
+    /// Quotes through another Quoter V2 deployment, e.g. on a local chain.
+    pub fn with_quoter(mut self, quoter: Address) -> Self {
+        self.quoter = quoter;
+        self
+    }
+
+    /// Calls the quoter with any of its functions, e.g. `quoteExactOutput`.
+    ///
+    /// The fee fields are left out, the node then runs the call at a zero gas price
+    /// rather than checking a fee against the base fee of the pinned block.
+    pub async fn call(&self, calldata: Bytes) -> anyhow::Result<Bytes> {
+        let tx = TransactionRequest::default()
+            .from(ME)
+            .to(self.quoter)
+            .with_input(calldata)
+            .nonce(0)
+            .gas_limit(1000000);
+        Ok(self
+            .provider
+            .call(tx)
+            .block(BlockId::number(self.block))
+            .await?)
+    }
+}
+
+impl QuoteBackend for EthCallBackend {
+    async fn quote(&self, request: QuoteRequest) -> anyhow::Result<QuoteResult> {
+        request.decode(self.call(request.calldata()).await?)
+    }
+
+    /// Sends all the calls at once.
+    async fn quote_batch(&self, requests: Vec<QuoteRequest>) -> anyhow::Result<Vec<QuoteResult>> {
+        let mut calls = JoinSet::new();
+        for (index, request) in requests.iter().copied().enumerate() {
+            let backend = self.clone();
+            calls.spawn(async move { (index, backend.quote(request).await) });
+        }
+
+        let mut quotes = vec![None; requests.len()];
+        while let Some(call) = calls.join_next().await {
+            let (index, quote) = call?;
+            quotes[index] = Some(quote?);
+        }
+        Ok(quotes.into_iter().flatten().collect())
+    }
+}
+
+/// `eth_call` to the Quoter V2 on a local Anvil fork of the node,
+/// the fork is killed when the backend is dropped.
+pub struct AnvilBackend {
+    inner: EthCallBackend,
+    _anvil: AnvilInstance,
+}
+
+impl AnvilBackend {
+    /// Forks `rpc_url` at `block`, needs `anvil` in `PATH`.
+    pub async fn spawn(rpc_url: Url, block: u64) -> anyhow::Result<Self> {
+        let anvil = Anvil::new()
+            .fork(rpc_url)
+            .fork_block_number(block)
+            .block_time(1u64)
+            .try_spawn()?;
+        let provider = Arc::new(ProviderBuilder::new().connect_http(anvil.endpoint_url()));
+        Ok(Self {
+            inner: EthCallBackend::new(provider, block),
+            _anvil: anvil,
+        })
+    }
+
+    pub fn with_quoter(mut self, quoter: Address) -> Self {
+        self.inner = self.inner.with_quoter(quoter);
+        self
+    }
+
+    pub async fn call(&self, calldata: Bytes) -> anyhow::Result<Bytes> {
+        self.inner.call(calldata).await
+    }
+}
+
+impl QuoteBackend for AnvilBackend {
+    async fn quote(&self, request: QuoteRequest) -> anyhow::Result<QuoteResult> {
+        self.inner.quote(request).await
+    }
+
+    async fn quote_batch(&self, requests: Vec<QuoteRequest>) -> anyhow::Result<Vec<QuoteResult>> {
+        self.inner.quote_batch(requests).await
+    }
+}
+
+/// Quoter V2 in revm.
+pub struct RevmBackend {
+    simulator: RefCell<Simulator>,
+    quoter: Address,
+}
+
+impl RevmBackend {
+    pub fn new(simulator: Simulator) -> Self {
+        Self {
+            simulator: RefCell::new(simulator),
+            quoter: V3_QUOTER_ADDR,
+        }
+    }
+
+    /// Quotes through another Quoter V2 deployment, e.g. on a local chain.
+    pub fn with_quoter(mut self, quoter: Address) -> Self {
+        self.quoter = quoter;
+        self
+    }
+
+    /// All the state is fetched on demand.
+    pub async fn fetch(
+        provider: RevmProvider,
+        block: u64,
+        cache: EvmCache,
+    ) -> anyhow::Result<Self> {
+        let block_env = fetch_block_env(&provider, BlockId::number(block), &cache).await?;
+        let cache_db = init_cache_db(provider, BlockId::number(block), cache);
+        let simulator = Simulator::new(cache_db)
+            .with_caller(ME)
+            .with_block(block_env);
+        Ok(Self::new(simulator))
+    }
+
+    /// Preloads the quoter, the pools and their tokens (from the [`BytecodeRegistry`]
+    /// when it has them and they match the chain) and mocks the pool balances.
+    pub async fn prewarmed(
+        provider: RevmProvider,
+        block: u64,
+        cache: EvmCache,
+        pools: &[PoolKey],
+    ) -> anyhow::Result<Self> {
+        let block_env = fetch_block_env(&provider, BlockId::number(block), &cache).await?;
+        let mut cache_db = init_cache_db(provider.clone(), BlockId::number(block), cache);
+
+        // ETH balances and nonces are not relevant to our simulation,
+        // so the accounts are preloaded with zero values to save RPC calls.
+        init_account(V3_QUOTER_ADDR, &mut cache_db, provider.clone()).await?;
+
+        // Artifacts that don't match the chain, e.g. a local non-forked one, are fetched.
+        let registry = BytecodeRegistry::from_env()?
+            .verified(&provider, block, cache_db.db.cache())
+            .await?;
+
+        let mut tokens: Vec<_> = pools
+            .iter()
+            .flat_map(|pool| [pool.token0, pool.token1])
+            .collect();
+        tokens.sort();
+        tokens.dedup();
+
+        for pool in pools {
+            init_account(pool.address, &mut cache_db, provider.clone()).await?;
+        }
+        for &token in &tokens {
+            let artifact = registry
+                .artifacts()
+                .find(|artifact| artifact.address == Some(token));
+            match artifact {
+                Some(artifact) => {
+                    init_account_with_bytecode(token, artifact.bytecode.clone(), &mut cache_db)?;
+                    init_proxy_implementation(token, &mut cache_db, provider.clone()).await?;
+                }
+                None => {
+                    init_account(token, &mut cache_db, provider.clone()).await?;
+                }
+            }
+        }
+
+        let mut simulator = Simulator::new(cache_db)
+            .with_caller(ME)
+            .with_block(block_env);
+
+        let mocked_balance = U256::MAX.div(U256::from(2));
+        for pool in pools {
+            for token in [pool.token0, pool.token1] {
+                set_erc20_balance(&mut simulator, token, pool.address, mocked_balance)?;
+            }
+        }
+
+        Ok(Self::new(simulator))
+    }
+
+    pub fn into_simulator(self) -> Simulator {
+        self.simulator.into_inner()
+    }
+
+    /// Calls the quoter with any of its functions, e.g. `quoteExactOutput`.
+    pub fn call(&self, calldata: Bytes) -> anyhow::Result<Bytes> {
+        Ok(self.simulator.borrow_mut().call(self.quoter, calldata)?)
+    }
+}
+
+impl QuoteBackend for RevmBackend {
+    async fn quote(&self, request: QuoteRequest) -> anyhow::Result<QuoteResult> {
+        request.decode(self.call(request.calldata())?)
+    }
+}
+
+/// Native Uniswap V3 math over the pool storage.
+///
+/// There is no gas model, `gas_estimate` is always zero.
+pub struct NativeBackend {
+    pools: Vec<(PoolKey, UniV3Pool)>,
+}
+
+impl NativeBackend {
+    pub fn new() -> Self {
+        Self { pools: Vec::new() }
+    }
+
+    pub fn insert(&mut self, key: PoolKey, pool: UniV3Pool) {
+        self.pools.push((key, pool));
+    }
+
+    /// Loads the pools from storage, see [`UniV3Pool::load`] for `word_radius`.
+    pub fn load<DB>(db: &mut DB, pools: &[PoolKey], word_radius: i16) -> anyhow::Result<Self>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let mut backend = Self::new();
+        for key in pools {
+            let pool = UniV3Pool::load(db, key.address, key.fee, word_radius)?;
+            backend.insert(*key, pool);
+        }
+        Ok(backend)
+    }
+}
+
+impl Default for NativeBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuoteBackend for NativeBackend {
+    async fn quote(&self, request: QuoteRequest) -> anyhow::Result<QuoteResult> {
+        let pool = self
+            .pools
+            .iter()
+            .find_map(|(key, pool)| key.matches(&request).then_some(pool))
+            .ok_or_else(|| {
+                anyhow!(
+                    "no pool loaded for {} / {} with fee {}",
+                    request.token_in,
+                    request.token_out,
+                    request.fee
+                )
+            })?;
+
+        // The same limit as the quoter calldata, so both report the same partial fills.
+        let zero_for_one = request.zero_for_one();
+        let limit = request
+            .sqrt_price_limit_x96
+            .unwrap_or_else(|| default_sqrt_price_limit_x96(zero_for_one));
+        let result = pool.swap(
+            zero_for_one,
+            I256::try_from(request.amount_in)?,
+            Some(U256::from(limit)),
+        )?;
+        let received = if zero_for_one {
+            result.amount1
+        } else {
+            result.amount0
+        };
+        Ok(QuoteResult {
+            amount_out: received.unsigned_abs(),
+            sqrt_price_x96_after: result.sqrt_price_x96_after.to(),
+            ticks_crossed: result.ticks_crossed,
+            gas_estimate: U256::ZERO,
+            partial_fill: result.sqrt_price_x96_after == U256::from(limit),
+        })
+    }
+}
@@ -1,24 +1,52 @@
 use anyhow::anyhow;
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
 use alloy::{
+    consensus::Header,
+    eips::{BlockId, BlockNumberOrTag},
     network::Ethereum,
-    primitives::{Address, Bytes, U256},
+    primitives::{Address, B256, Bytes, U256, b256},
     providers::{
         Identity, Provider, RootProvider,
+        ext::DebugApi,
         fillers::{BlobGasFiller, ChainIdFiller, FillProvider, GasFiller, JoinFill, NonceFiller},
     },
+    rpc::types::{
+        TransactionRequest,
+        trace::{
+            common::TraceResult,
+            geth::{DiffMode, GethDebugTracingOptions, PreStateConfig, PreStateFrame},
+        },
+    },
     sol_types::SolValue,
 };
 use revm::{
-    Context, ExecuteEvm, MainBuilder, MainContext,
-    context::result::{ExecutionResult, Output},
+    Context, Database, DatabaseCommit, DatabaseRef, ExecuteCommitEvm, ExecuteEvm, MainBuilder,
+    MainContext, MainnetEvm,
+    context::{BlockEnv, ContextTr, TxEnv, result::ExecutionResult},
     database::{AlloyDB, CacheDB, WrapDatabaseAsync},
-    primitives::{TxKind, keccak256},
+    handler::MainnetContext,
+    primitives::{
+        TxKind,
+        eip4844::{BLOB_BASE_FEE_UPDATE_FRACTION_CANCUN, BLOB_BASE_FEE_UPDATE_FRACTION_PRAGUE},
+        keccak256,
+    },
     state::{AccountInfo, Bytecode},
 };
 
-pub type AlloyCacheDB = CacheDB<WrapDatabaseAsync<AlloyDB<Ethereum, RevmProvider>>>;
+use crate::{
+    abi::{decode_implementation_response, implementation_calldata},
+    address::ME,
+    cache::{DiskCache, EvmCache},
+    error::SimError,
+};
+
+pub type AlloyDiskDB = DiskCache<WrapDatabaseAsync<AlloyDB<Ethereum, RevmProvider>>>;
+
+pub type AlloyCacheDB = CacheDB<AlloyDiskDB>;
 
 pub type RevmProvider = Arc<
     FillProvider<
@@ -30,43 +58,389 @@ pub type RevmProvider = Arc<
     >,
 >;
 
-pub fn init_cache_db(provider: RevmProvider) -> AlloyCacheDB {
-    CacheDB::new(WrapDatabaseAsync::new(AlloyDB::new(provider, Default::default())).unwrap())
+/// Creates a cache DB that reads the state at the given block.
+///
+/// Pass a block number or hash (rather than "latest") to keep
+/// all the reads of a sweep consistent. State read at a block number
+/// is also persisted on disk, see [`DiskCache`].
+pub fn init_cache_db(provider: RevmProvider, block: BlockId, cache: EvmCache) -> AlloyCacheDB {
+    CacheDB::new(init_disk_db(provider, block, cache))
 }
 
-pub fn revm_call(
-    from: Address,
-    to: Address,
-    calldata: Bytes,
-    cache_db: &mut AlloyCacheDB,
-) -> anyhow::Result<Bytes> {
-    let mut evm = Context::mainnet()
-        .with_db(cache_db)
-        .modify_tx_chained(|tx| {
-            tx.caller = from;
-            tx.kind = TxKind::Call(to);
-            tx.data = calldata;
-            tx.value = U256::ZERO;
-        })
-        .build_mainnet();
-
-    let ref_tx = evm.replay()?;
-    let result = ref_tx.result;
-
-    let value = match result {
-        ExecutionResult::Success {
-            output: Output::Call(value),
-            ..
-        } => value,
-        result => {
-            return Err(anyhow!("execution failed: {result:?}"));
-        }
+fn init_disk_db(provider: RevmProvider, block: BlockId, cache: EvmCache) -> AlloyDiskDB {
+    let alloy_db = WrapDatabaseAsync::new(AlloyDB::new(provider, block)).unwrap();
+    DiskCache::new(alloy_db, cache, block)
+}
+
+/// Fetches the block header and converts it into the revm block environment.
+///
+/// Environments of numbered blocks are cached on disk.
+pub async fn fetch_block_env(
+    provider: &RevmProvider,
+    block: BlockId,
+    cache: &EvmCache,
+) -> anyhow::Result<BlockEnv> {
+    let cache_key = block.as_u64().map(|number| format!("block_env-{number}"));
+    if let Some(cache_key) = &cache_key
+        && let Ok(bytes) = cache.read(cache_key).await
+    {
+        return Ok(serde_json::from_slice(&bytes)?);
+    }
+
+    let header = provider
+        .get_block(block)
+        .await?
+        .ok_or_else(|| anyhow!("block {block} not found"))?
+        .header;
+
+    let block_env = block_env(&header);
+
+    if let Some(cache_key) = cache_key {
+        cache
+            .write(&cache_key, serde_json::to_vec(&block_env)?)
+            .await?;
+    }
+
+    Ok(block_env)
+}
+
+fn block_env(header: &Header) -> BlockEnv {
+    let mut block_env = BlockEnv {
+        number: U256::from(header.number),
+        beneficiary: header.beneficiary,
+        timestamp: U256::from(header.timestamp),
+        gas_limit: header.gas_limit,
+        basefee: header.base_fee_per_gas.unwrap_or_default(),
+        difficulty: header.difficulty,
+        prevrandao: Some(header.mix_hash),
+        ..Default::default()
     };
+    if let Some(excess_blob_gas) = header.excess_blob_gas {
+        // EIP-7685 requests hash is only in Prague headers, Cancun blocks
+        // have blob gas but the lower update fraction.
+        let update_fraction = match header.requests_hash {
+            Some(_) => BLOB_BASE_FEE_UPDATE_FRACTION_PRAGUE,
+            None => BLOB_BASE_FEE_UPDATE_FRACTION_CANCUN,
+        };
+        block_env.set_blob_excess_gas_and_price(excess_blob_gas, update_fraction);
+    }
+    block_env
+}
+
+/// Returns the median priority fee paid by the transactions of the given block.
+///
+/// Priority fees of numbered blocks are cached on disk.
+pub async fn fetch_priority_fee(
+    provider: &RevmProvider,
+    block: u64,
+    cache: &EvmCache,
+) -> anyhow::Result<u128> {
+    let cache_key = format!("priority_fee-{block}");
+    if let Ok(bytes) = cache.read(&cache_key).await {
+        return Ok(serde_json::from_slice(&bytes)?);
+    }
+
+    let fee_history = provider
+        .get_fee_history(1, BlockNumberOrTag::Number(block), &[50.0])
+        .await?;
+    let priority_fee = fee_history
+        .reward
+        .and_then(|rewards| rewards.first()?.first().copied())
+        .ok_or_else(|| anyhow!("no priority fees for block {block}"))?;
+
+    cache
+        .write(&cache_key, serde_json::to_vec(&priority_fee)?)
+        .await?;
+
+    Ok(priority_fee)
+}
+
+/// Returns accounts and storage slots modified by the transactions of the given block.
+///
+/// Relies on `debug_traceBlockByNumber` with `prestateTracer` in diff mode,
+/// not all nodes support this call.
+pub async fn fetch_touched_state(
+    provider: &RevmProvider,
+    block: u64,
+) -> anyhow::Result<BTreeMap<Address, BTreeSet<B256>>> {
+    let options = GethDebugTracingOptions::prestate_tracer(PreStateConfig {
+        diff_mode: Some(true),
+        disable_code: Some(true),
+        disable_storage: None,
+    });
+    let traces = provider
+        .debug_trace_block_by_number(block.into(), options)
+        .await?;
+
+    let mut touched = BTreeMap::new();
+    for trace in traces {
+        let frame = match trace {
+            TraceResult::Success { result, .. } => result.try_into_pre_state_frame()?,
+            TraceResult::Error { error, tx_hash } => {
+                return Err(anyhow!("failed to trace {tx_hash:?}: {error}"));
+            }
+        };
+        let PreStateFrame::Diff(diff) = frame else {
+            return Err(anyhow!("expected prestate diff"));
+        };
+        add_touched_state(&mut touched, diff);
+    }
 
-    Ok(value)
+    Ok(touched)
 }
 
-const CACHE_DIR: &str = ".evm_cache";
+/// Adds the accounts and storage slots of a prestate diff.
+fn add_touched_state(touched: &mut BTreeMap<Address, BTreeSet<B256>>, diff: DiffMode) {
+    for (address, state) in diff.pre.into_iter().chain(diff.post) {
+        touched
+            .entry(address)
+            .or_default()
+            .extend(state.storage.into_keys());
+    }
+}
+
+/// Reusable mainnet EVM that owns the cache DB, the block environment
+/// and the default caller, so consecutive calls don't rebuild the context.
+pub struct Simulator<DB: Database = AlloyCacheDB> {
+    evm: MainnetEvm<MainnetContext<DB>>,
+    caller: Address,
+    /// Storage slots mocked with [`Simulator::override_storage`].
+    overrides: BTreeMap<Address, BTreeSet<U256>>,
+}
+
+impl<DB> Simulator<DB>
+where
+    DB: Database + DatabaseCommit,
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
+    pub fn new(db: DB) -> Self {
+        let evm = Context::mainnet()
+            .with_db(db)
+            // Behave like eth_call: calls are sent with zero gas price from a caller
+            // with mocked zero balance, and the caller nonce is bumped by `transact_commit`.
+            .modify_cfg_chained(|cfg| {
+                cfg.disable_nonce_check = true;
+                cfg.disable_balance_check = true;
+                cfg.disable_base_fee = true;
+            })
+            .build_mainnet();
+
+        Self {
+            evm,
+            caller: ME,
+            overrides: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_caller(mut self, caller: Address) -> Self {
+        self.caller = caller;
+        self
+    }
+
+    pub fn with_block(mut self, block: BlockEnv) -> Self {
+        self.evm.set_block(block);
+        self
+    }
+
+    pub fn caller(&self) -> Address {
+        self.caller
+    }
+
+    pub fn block(&self) -> &BlockEnv {
+        &self.evm.block
+    }
+
+    pub fn db(&self) -> &DB {
+        self.evm.db_ref()
+    }
+
+    pub fn db_mut(&mut self) -> &mut DB {
+        self.evm.db_mut()
+    }
+
+    /// Executes a call without committing its state changes.
+    pub fn transact(&mut self, to: Address, calldata: Bytes) -> Result<ExecutionResult, SimError> {
+        let tx = self.tx(to, calldata);
+        let result = self.evm.transact(tx)?;
+        Ok(result.result)
+    }
+
+    /// Executes a call and returns its output, fails unless it succeeds.
+    pub fn call(&mut self, to: Address, calldata: Bytes) -> Result<Bytes, SimError> {
+        match self.transact(to, calldata)? {
+            ExecutionResult::Success { output, .. } => Ok(output.into_data()),
+            ExecutionResult::Revert { output, gas_used } => Err(SimError::revert(output, gas_used)),
+            ExecutionResult::Halt { reason, gas_used } => Err(SimError::Halt { reason, gas_used }),
+        }
+    }
+
+    /// Executes a call that is expected to REVERT,
+    /// returns the output of REVERT.
+    pub fn call_expecting_revert(
+        &mut self,
+        to: Address,
+        calldata: Bytes,
+    ) -> Result<Bytes, SimError> {
+        match self.transact(to, calldata)? {
+            ExecutionResult::Revert { output, .. } => Ok(output),
+            ExecutionResult::Success {
+                output, gas_used, ..
+            } => Err(SimError::UnexpectedSuccess {
+                output: output.into_data(),
+                gas_used,
+            }),
+            ExecutionResult::Halt { reason, gas_used } => Err(SimError::Halt { reason, gas_used }),
+        }
+    }
+
+    /// Executes a call and commits its state changes to the DB.
+    pub fn transact_commit(
+        &mut self,
+        to: Address,
+        calldata: Bytes,
+    ) -> Result<ExecutionResult, SimError> {
+        let tx = self.tx(to, calldata);
+        let result = self.evm.transact_commit(tx)?;
+        Ok(result)
+    }
+
+    /// Returns gas used by a successful call.
+    pub fn estimate_gas(&mut self, to: Address, calldata: Bytes) -> Result<u64, SimError> {
+        match self.transact(to, calldata)? {
+            ExecutionResult::Success { gas_used, .. } => Ok(gas_used),
+            ExecutionResult::Revert { output, gas_used } => Err(SimError::revert(output, gas_used)),
+            ExecutionResult::Halt { reason, gas_used } => Err(SimError::Halt { reason, gas_used }),
+        }
+    }
+
+    fn tx(&self, to: Address, calldata: Bytes) -> TxEnv {
+        TxEnv {
+            caller: self.caller,
+            kind: TxKind::Call(to),
+            data: calldata,
+            value: U256::ZERO,
+            ..Default::default()
+        }
+    }
+}
+
+impl<ExtDB> Simulator<CacheDB<ExtDB>>
+where
+    ExtDB: DatabaseRef,
+    ExtDB::Error: std::error::Error + Send + Sync + 'static,
+{
+    /// Mocks a storage slot. Unlike slots written to the DB directly,
+    /// overrides are kept by [`Simulator::advance_to`] even if the slot changes on chain.
+    pub fn override_storage(
+        &mut self,
+        address: Address,
+        slot: U256,
+        value: U256,
+    ) -> Result<(), ExtDB::Error> {
+        self.db_mut().insert_account_storage(address, slot, value)?;
+        self.overrides.entry(address).or_default().insert(slot);
+        Ok(())
+    }
+
+    /// Drops the cached state modified on chain, the DB must already read the new block.
+    ///
+    /// Touched storage slots are refetched lazily unless overridden. Touched contracts
+    /// get their balance and nonce refetched but keep their (possibly mocked) bytecode,
+    /// touched EOAs are dropped altogether.
+    fn invalidate_touched_state(
+        &mut self,
+        touched: BTreeMap<Address, BTreeSet<B256>>,
+    ) -> Result<(), ExtDB::Error> {
+        let cache_db = self.evm.db_mut();
+        for (address, slots) in touched {
+            let Some(account) = cache_db.cache.accounts.get_mut(&address) else {
+                continue;
+            };
+            if account.info.is_empty_code_hash() {
+                // Balance and nonce of an EOA are the only state it has.
+                cache_db.cache.accounts.remove(&address);
+                continue;
+            }
+
+            let overrides = self.overrides.get(&address);
+            for slot in slots {
+                let slot = U256::from_be_bytes(slot.0);
+                if !overrides.is_some_and(|overrides| overrides.contains(&slot)) {
+                    account.storage.remove(&slot);
+                }
+            }
+
+            let info = cache_db.db.basic_ref(address)?.unwrap_or_default();
+            account.info.balance = info.balance;
+            account.info.nonce = info.nonce;
+        }
+        Ok(())
+    }
+}
+
+impl Simulator<AlloyCacheDB> {
+    /// Moves the simulation to a later block.
+    ///
+    /// Only the cached state modified by the blocks in between is dropped,
+    /// see [`Simulator::override_storage`] for the mocks that are kept.
+    pub async fn advance_to(
+        &mut self,
+        provider: RevmProvider,
+        block: BlockId,
+    ) -> anyhow::Result<()> {
+        let from: u64 = self.block().number.to();
+        if from == 0 {
+            return Err(anyhow!("simulator is not pinned to a block"));
+        }
+
+        let cache = self.db().db.cache().clone();
+        let block_env = fetch_block_env(&provider, block, &cache).await?;
+        let to: u64 = block_env.number.to();
+        if to <= from {
+            return Err(anyhow!("block {to} is not after {from}"));
+        }
+
+        let mut touched: BTreeMap<Address, BTreeSet<B256>> = BTreeMap::new();
+        for number in from + 1..=to {
+            for (address, slots) in fetch_touched_state(&provider, number).await? {
+                touched.entry(address).or_default().extend(slots);
+            }
+        }
+
+        self.db_mut().db = init_disk_db(provider, BlockId::number(to), cache);
+        self.invalidate_touched_state(touched)?;
+        self.evm.set_block(block_env);
+
+        Ok(())
+    }
+}
+
+/// `bytes32(uint256(keccak256("eip1967.proxy.implementation")) - 1)`
+const EIP1967_IMPLEMENTATION_SLOT: B256 =
+    b256!("360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+/// `bytes32(uint256(keccak256("eip1967.proxy.beacon")) - 1)`
+const EIP1967_BEACON_SLOT: B256 =
+    b256!("a3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50");
+/// `keccak256("PROXIABLE")`
+const EIP1822_PROXIABLE_SLOT: B256 =
+    b256!("c5f16f0fcc639fa48a6947836d9850f504798523bf8c9a3a87d5876cf622bcf7");
+/// `keccak256("org.zeppelinos.proxy.implementation")`, used by the
+/// OpenZeppelin transparent proxies deployed before EIP-1967 (e.g. USDC).
+const ZEPPELINOS_IMPLEMENTATION_SLOT: B256 =
+    b256!("7050c9e0f4ca769c69bd3a8ef740bc37934f8e2c036e5a723fd8ee048ed3f8c3");
+
+/// Proxies of proxies are followed up to this depth.
+pub const MAX_PROXY_DEPTH: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyKind {
+    Eip1967,
+    Eip1822,
+    /// OpenZeppelin transparent proxy with the pre EIP-1967 slot.
+    Transparent,
+    /// EIP-1967 beacon proxy, the implementation is returned by the beacon.
+    Beacon,
+}
 
 // Warning: always make sure to compare the
 // results of your simulations with standard eth_call.
@@ -74,14 +448,127 @@ const CACHE_DIR: &str = ".evm_cache";
 /// Reduces number of RPC calls by:
 /// * Caching contract's bytecode.
 /// * Mocking account's balance and nonce with zero values.
+/// * Preloading the implementation of a proxy, see [`init_proxy_implementation`].
+///
+/// Returns the implementation address if the account is a proxy.
 pub async fn init_account(
     address: Address,
     cache_db: &mut AlloyCacheDB,
     provider: RevmProvider,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Option<Address>> {
+    let bytecode = fetch_bytecode(address, cache_db, &provider).await?;
+    let is_empty = bytecode.is_empty();
+    init_account_with_bytecode(address, bytecode, cache_db)?;
+
+    if is_empty {
+        return Ok(None);
+    }
+    init_proxy_implementation(address, cache_db, provider).await
+}
+
+/// Detects whether the account is a proxy and preloads the bytecode of its
+/// implementation (recursively, for proxies of proxies) like [`init_account`].
+///
+/// Returns the address of the final implementation.
+pub async fn init_proxy_implementation(
+    address: Address,
+    cache_db: &mut AlloyCacheDB,
+    provider: RevmProvider,
+) -> anyhow::Result<Option<Address>> {
+    let mut resolved = None;
+    let mut proxy = address;
+    for _ in 0..MAX_PROXY_DEPTH {
+        let Some((kind, implementation)) = detect_proxy(proxy, cache_db, &provider).await? else {
+            break;
+        };
+        tracing::debug!("{proxy} is a {kind:?} proxy of {implementation}");
+
+        let bytecode = fetch_bytecode(implementation, cache_db, &provider).await?;
+        init_account_with_bytecode(implementation, bytecode, cache_db)?;
+
+        resolved = Some(implementation);
+        proxy = implementation;
+    }
+    Ok(resolved)
+}
+
+/// Returns the kind and the implementation of a proxy.
+pub async fn detect_proxy(
+    address: Address,
+    cache_db: &mut AlloyCacheDB,
+    provider: &RevmProvider,
+) -> anyhow::Result<Option<(ProxyKind, Address)>> {
+    let beacon = match proxy_slot(cache_db, address)? {
+        Some((ProxyKind::Beacon, beacon)) => beacon,
+        proxy => return Ok(proxy),
+    };
+    let implementation = fetch_beacon_implementation(beacon, cache_db, provider).await?;
+    Ok(Some((ProxyKind::Beacon, implementation)))
+}
+
+/// Asks the beacon for the implementation, cached on disk like the state
+/// when the block is pinned.
+async fn fetch_beacon_implementation(
+    beacon: Address,
+    cache_db: &AlloyCacheDB,
+    provider: &RevmProvider,
+) -> anyhow::Result<Address> {
+    let cache = cache_db.db.cache();
+    let number = cache_db.db.block();
+    let cache_key = number.map(|number| format!("beacon_implementation-{beacon:?}-{number}"));
+    if let Some(cache_key) = &cache_key
+        && let Ok(bytes) = cache.read(cache_key).await
+    {
+        return Ok(serde_json::from_slice(&bytes)?);
+    }
+
+    let tx = TransactionRequest::default()
+        .to(beacon)
+        .input(implementation_calldata().into());
+    let block = number.map_or(BlockId::latest(), BlockId::number);
+    // eth_call
+    let response = provider.call(tx).block(block).await?;
+    let implementation = decode_implementation_response(response)?;
+
+    if let Some(cache_key) = cache_key {
+        cache
+            .write(&cache_key, serde_json::to_vec(&implementation)?)
+            .await?;
+    }
+    Ok(implementation)
+}
+
+/// Returns the kind and the implementation stored in the proxy slots,
+/// the beacon rather than the implementation for [`ProxyKind::Beacon`].
+pub fn proxy_slot<DB>(db: &mut DB, address: Address) -> anyhow::Result<Option<(ProxyKind, Address)>>
+where
+    DB: Database,
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let slots = [
+        (ProxyKind::Eip1967, EIP1967_IMPLEMENTATION_SLOT),
+        (ProxyKind::Eip1822, EIP1822_PROXIABLE_SLOT),
+        (ProxyKind::Transparent, ZEPPELINOS_IMPLEMENTATION_SLOT),
+        (ProxyKind::Beacon, EIP1967_BEACON_SLOT),
+    ];
+    for (kind, slot) in slots {
+        let value = db.storage(address, slot.into())?;
+        if !value.is_zero() {
+            return Ok(Some((kind, Address::from_word(value.into()))));
+        }
+    }
+    Ok(None)
+}
+
+async fn fetch_bytecode(
+    address: Address,
+    cache_db: &AlloyCacheDB,
+    provider: &RevmProvider,
+) -> anyhow::Result<Bytecode> {
     let cache_key = format!("bytecode-{:?}", address);
 
-    let bytecode = match cacache::read(CACHE_DIR, cache_key.clone()).await {
+    let cache = cache_db.db.cache();
+    let bytecode = match cache.read(&cache_key).await {
         Ok(bytes) => {
             let bytes = Bytes::from(bytes);
             Bytecode::new_raw(bytes)
@@ -90,12 +577,11 @@ pub async fn init_account(
             // eth_getCode
             let bytes = provider.get_code_at(address).await?;
             let bytecode = Bytecode::new_raw(bytes.clone());
-            cacache::write(CACHE_DIR, cache_key, bytes.to_vec()).await?;
+            cache.write(&cache_key, &bytes).await?;
             bytecode
         }
     };
-
-    init_account_with_bytecode(address, bytecode, cache_db)
+    Ok(bytecode)
 }
 
 /// Mocks account balance and nonce that prevents extra
@@ -118,7 +604,9 @@ pub fn init_account_with_bytecode(
     Ok(())
 }
 
-/// Mocks account balance.
+/// Mocks account balance, `slot` is the slot of the Solidity balances mapping.
+///
+/// See [`crate::erc20::set_erc20_balance`] when the slot is not known.
 pub fn insert_mapping_storage_slot(
     contract: Address,
     slot: U256,
@@ -131,31 +619,232 @@ pub fn insert_mapping_storage_slot(
     Ok(())
 }
 
-/// Send a transaction and expects the REVERT,
-/// returns the output of REVERT.
-pub fn revm_revert(
-    from: Address,
-    to: Address,
-    calldata: Bytes,
-    cache_db: &mut AlloyCacheDB,
-) -> anyhow::Result<Bytes> {
-    let mut evm = Context::mainnet()
-        .with_db(cache_db)
-        .modify_tx_chained(|tx| {
-            tx.caller = from;
-            tx.kind = TxKind::Call(to);
-            tx.data = calldata;
-            tx.value = U256::ZERO;
-        })
-        .build_mainnet();
-
-    let ref_tx = evm.replay()?;
-    let result = ref_tx.result;
-
-    let value = match result {
-        ExecutionResult::Revert { output: value, .. } => value,
-        _ => unreachable!(),
+#[cfg(test)]
+mod tests {
+    use alloy::{primitives::address, rpc::types::trace::geth::AccountState};
+    use revm::{
+        DatabaseRef,
+        context::result::HaltReason,
+        database::{CacheDB, EmptyDB},
     };
 
-    Ok(value)
+    use super::*;
+    use crate::test_support::{Asm, insert_code, op::*};
+
+    const CONTRACT: Address = address!("0000000000000000000000000000000000001234");
+
+    fn simulator(code: Bytes) -> Simulator<CacheDB<EmptyDB>> {
+        let mut db = CacheDB::new(EmptyDB::default());
+        insert_code(&mut db, CONTRACT, code);
+        Simulator::new(db)
+    }
+
+    /// Increments the counter in slot 0 and returns it.
+    fn counter_code() -> Bytes {
+        let mut asm = Asm::default();
+        asm.ops(&[
+            PUSH1, 0x00, SLOAD, PUSH1, 0x01, ADD, DUP1, PUSH1, 0x00, SSTORE,
+        ])
+        .return_word();
+        asm.finish()
+    }
+
+    /// Reverts with `Error("nope")`.
+    fn revert_code() -> Bytes {
+        let mut asm = Asm::default();
+        asm.push_selector([0x08, 0xc3, 0x79, 0xa0])
+            .ops(&[PUSH1, 0x00, MSTORE])
+            .ops(&[PUSH1, 0x20, PUSH1, 0x04, MSTORE])
+            .ops(&[PUSH1, 0x04, PUSH1, 0x24, MSTORE])
+            .push_selector(*b"nope")
+            .ops(&[PUSH1, 0x44, MSTORE])
+            .ops(&[PUSH1, 0x64, PUSH1, 0x00, REVERT]);
+        asm.finish()
+    }
+
+    fn counter(simulator: &Simulator<CacheDB<EmptyDB>>) -> U256 {
+        simulator.db().storage_ref(CONTRACT, U256::ZERO).unwrap()
+    }
+
+    fn nonce(simulator: &Simulator<CacheDB<EmptyDB>>) -> u64 {
+        let caller = simulator.caller();
+        simulator
+            .db()
+            .basic_ref(caller)
+            .unwrap()
+            .map_or(0, |info| info.nonce)
+    }
+
+    #[test]
+    fn invalidates_touched_state() {
+        let eoa = address!("000000000000000000000000000000000000beef");
+        let slot = |slot: u64| U256::from(slot);
+        let touched_slot = |slot: u64| AccountState {
+            storage: [(B256::from(U256::from(slot)), B256::ZERO)].into(),
+            ..Default::default()
+        };
+
+        // The chain at the new block.
+        let mut chain = CacheDB::new(EmptyDB::default());
+        let code = Bytecode::new_raw(Bytes::from_static(&[STOP]));
+        chain.insert_account_info(
+            CONTRACT,
+            AccountInfo {
+                balance: U256::from(9),
+                nonce: 2,
+                code_hash: code.hash_slow(),
+                code: Some(code),
+            },
+        );
+        for (index, value) in [(1, 10), (2, 20), (3, 30)] {
+            chain
+                .insert_account_storage(CONTRACT, slot(index), U256::from(value))
+                .unwrap();
+        }
+        chain.insert_account_info(eoa, AccountInfo::from_balance(U256::from(8)));
+
+        // Cached at the old block, with mocked code and an overridden slot.
+        let mut db = CacheDB::new(chain);
+        insert_code(&mut db, CONTRACT, counter_code());
+        db.insert_account_storage(CONTRACT, slot(1), U256::from(1))
+            .unwrap();
+        db.insert_account_storage(CONTRACT, slot(2), U256::from(2))
+            .unwrap();
+        db.insert_account_info(eoa, AccountInfo::from_balance(U256::from(5)));
+        let mut simulator = Simulator::new(db);
+        simulator
+            .override_storage(CONTRACT, slot(3), U256::from(3))
+            .unwrap();
+
+        let diff = DiffMode {
+            pre: [(CONTRACT, touched_slot(1)), (eoa, AccountState::default())].into(),
+            post: [(CONTRACT, touched_slot(3))].into(),
+        };
+        let mut touched = BTreeMap::new();
+        add_touched_state(&mut touched, diff);
+        simulator.invalidate_touched_state(touched).unwrap();
+
+        let db = simulator.db();
+        // Touched slots are refetched, except the overridden one.
+        assert_eq!(db.storage_ref(CONTRACT, slot(1)).unwrap(), U256::from(10));
+        assert_eq!(db.storage_ref(CONTRACT, slot(2)).unwrap(), U256::from(2));
+        assert_eq!(db.storage_ref(CONTRACT, slot(3)).unwrap(), U256::from(3));
+
+        // Balance and nonce are refetched, the mocked code is kept.
+        let info = db.basic_ref(CONTRACT).unwrap().unwrap();
+        assert_eq!((info.balance, info.nonce), (U256::from(9), 2));
+        assert_eq!(info.code_hash, keccak256(counter_code()));
+
+        assert_eq!(db.basic_ref(eoa).unwrap().unwrap().balance, U256::from(8));
+    }
+
+    #[test]
+    fn prices_blob_gas_by_fork() {
+        let cancun = Header {
+            excess_blob_gas: Some(10_000_000),
+            ..Default::default()
+        };
+        let prague = Header {
+            requests_hash: Some(B256::ZERO),
+            ..cancun.clone()
+        };
+
+        let blob_gasprice = |header: &Header| {
+            block_env(header)
+                .blob_excess_gas_and_price
+                .map(|blob| blob.blob_gasprice)
+        };
+        assert_eq!(blob_gasprice(&cancun), Some(19));
+        assert_eq!(blob_gasprice(&prague), Some(7));
+        // Pre-Cancun blocks keep the default minimum price.
+        assert_eq!(blob_gasprice(&Header::default()), Some(1));
+    }
+
+    #[test]
+    fn maps_results_to_sim_errors() {
+        let mut success = simulator(counter_code());
+        assert_eq!(
+            success.call(CONTRACT, Bytes::new()).unwrap(),
+            U256::from(1).to_be_bytes_vec()
+        );
+        assert!(success.estimate_gas(CONTRACT, Bytes::new()).unwrap() > 21_000);
+        assert!(matches!(
+            success.call_expecting_revert(CONTRACT, Bytes::new()),
+            Err(SimError::UnexpectedSuccess { .. })
+        ));
+
+        let mut revert = simulator(revert_code());
+        let error = revert.call(CONTRACT, Bytes::new()).unwrap_err();
+        assert!(
+            matches!(&error, SimError::Revert { reason: Some(reason), .. } if reason == "revert: nope"),
+            "{error:?}"
+        );
+        assert!(
+            revert
+                .estimate_gas(CONTRACT, Bytes::new())
+                .unwrap_err()
+                .is_revert()
+        );
+        let output = revert
+            .call_expecting_revert(CONTRACT, Bytes::new())
+            .unwrap();
+        assert_eq!(&output[..4], &[0x08, 0xc3, 0x79, 0xa0]);
+
+        let mut halt = simulator(Bytes::from_static(&[INVALID]));
+        for error in [
+            halt.call(CONTRACT, Bytes::new()).unwrap_err(),
+            halt.call_expecting_revert(CONTRACT, Bytes::new())
+                .unwrap_err(),
+            halt.estimate_gas(CONTRACT, Bytes::new()).unwrap_err(),
+        ] {
+            assert!(
+                matches!(
+                    error,
+                    SimError::Halt {
+                        reason: HaltReason::InvalidFEOpcode,
+                        ..
+                    }
+                ),
+                "{error:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn commits_only_with_transact_commit() {
+        let mut simulator = simulator(counter_code());
+
+        for _ in 0..2 {
+            let result = simulator.transact(CONTRACT, Bytes::new()).unwrap();
+            assert_eq!(
+                result.output().unwrap(),
+                &U256::from(1).to_be_bytes_vec()[..]
+            );
+        }
+        assert_eq!(counter(&simulator), U256::ZERO);
+
+        for expected in 1..=2 {
+            let result = simulator.transact_commit(CONTRACT, Bytes::new()).unwrap();
+            assert!(result.is_success());
+            assert_eq!(counter(&simulator), U256::from(expected));
+        }
+        // Reads see the committed state.
+        assert_eq!(
+            simulator.call(CONTRACT, Bytes::new()).unwrap(),
+            U256::from(3).to_be_bytes_vec()
+        );
+    }
+
+    #[test]
+    fn bumps_caller_nonce_on_commit() {
+        let mut simulator = simulator(counter_code());
+
+        simulator.transact(CONTRACT, Bytes::new()).unwrap();
+        assert_eq!(nonce(&simulator), 0);
+
+        for expected in 1..=3 {
+            simulator.transact_commit(CONTRACT, Bytes::new()).unwrap();
+            assert_eq!(nonce(&simulator), expected);
+        }
+    }
 }
@@ -0,0 +1,313 @@
+//! Transport layer counting, timing and recording the JSON-RPC requests sent by a provider.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    providers::ProviderBuilder,
+    rpc::{
+        client::ClientBuilder,
+        json_rpc::{Id, RequestPacket, ResponsePacket, ResponsePayload, SerializedRequest},
+    },
+    transports::{TransportError, TransportFut, http::reqwest::Url},
+};
+use serde::Serialize;
+use tower::{Layer, Service};
+
+use crate::revm::RevmProvider;
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct MethodStats {
+    pub calls: u64,
+    /// Error responses and transport failures.
+    pub errors: u64,
+    pub total_time: Duration,
+    pub max_time: Duration,
+}
+
+/// A request with its outcome, kept only by [`RpcStats::recording`].
+#[derive(Debug, Clone, Serialize)]
+pub struct RpcRecord {
+    pub method: String,
+    pub params: Option<String>,
+    /// Raw result, or the error message.
+    pub response: Result<String, String>,
+    /// Time of the whole packet, the same for all the requests of a batch.
+    pub elapsed: Duration,
+}
+
+/// Stats collected since the creation or the last reset.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RpcSummary {
+    pub methods: BTreeMap<String, MethodStats>,
+    pub records: Vec<RpcRecord>,
+}
+
+impl RpcSummary {
+    pub fn counts(&self) -> BTreeMap<String, u64> {
+        self.methods
+            .iter()
+            .map(|(method, stats)| (method.clone(), stats.calls))
+            .collect()
+    }
+
+    pub fn count(&self, method: &str) -> u64 {
+        self.methods.get(method).map_or(0, |stats| stats.calls)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.methods.values().map(|stats| stats.calls).sum()
+    }
+}
+
+impl fmt::Display for RpcSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<28} {:>6} {:>6} {:>12} {:>12}",
+            "method", "calls", "errors", "total", "max"
+        )?;
+        for (method, stats) in &self.methods {
+            writeln!(
+                f,
+                "{:<28} {:>6} {:>6} {:>12} {:>12}",
+                method,
+                stats.calls,
+                stats.errors,
+                format!("{:.2?}", stats.total_time),
+                format!("{:.2?}", stats.max_time)
+            )?;
+        }
+        write!(f, "{} requests", self.total())
+    }
+}
+
+/// Stats shared by all the clones of the layer.
+#[derive(Debug, Clone, Default)]
+pub struct RpcStats {
+    summary: Arc<Mutex<RpcSummary>>,
+    record: bool,
+    /// Also gets every request, see [`RpcStats::scoped`].
+    parent: Option<Box<RpcStats>>,
+}
+
+impl RpcStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also keeps every request and response, see [`RpcSummary::records`].
+    pub fn recording() -> Self {
+        Self {
+            record: true,
+            ..Self::default()
+        }
+    }
+
+    /// Stats of their own that are also added to these ones, so they can be
+    /// reset between the phases of a benchmark without losing the totals.
+    pub fn scoped(&self) -> Self {
+        Self {
+            summary: Arc::default(),
+            record: self.record,
+            parent: Some(Box::new(self.clone())),
+        }
+    }
+
+    pub fn summary(&self) -> RpcSummary {
+        self.summary.lock().unwrap().clone()
+    }
+
+    /// Requests by method, batched requests are counted one by one.
+    pub fn counts(&self) -> BTreeMap<String, u64> {
+        self.summary.lock().unwrap().counts()
+    }
+
+    /// E.g. `count("eth_getCode")` to check that the bytecode came from the cache.
+    pub fn count(&self, method: &str) -> u64 {
+        self.summary.lock().unwrap().count(method)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.summary.lock().unwrap().total()
+    }
+
+    /// Returns the summary and starts over.
+    pub fn reset(&self) -> RpcSummary {
+        std::mem::take(&mut *self.summary.lock().unwrap())
+    }
+
+    fn finish(
+        &self,
+        request: &RequestPacket,
+        response: &Result<ResponsePacket, TransportError>,
+        elapsed: Duration,
+    ) {
+        let outcomes: HashMap<&Id, Result<String, String>> = match response {
+            Ok(response) => response
+                .responses()
+                .iter()
+                .map(|response| {
+                    let outcome = match &response.payload {
+                        ResponsePayload::Success(result) => Ok(result.get().to_string()),
+                        ResponsePayload::Failure(error) => Err(error.to_string()),
+                    };
+                    (&response.id, outcome)
+                })
+                .collect(),
+            Err(_) => HashMap::new(),
+        };
+
+        for request in request.requests() {
+            let outcome = match response {
+                Ok(_) => outcomes
+                    .get(request.id())
+                    .cloned()
+                    .unwrap_or_else(|| Err("no response".to_string())),
+                Err(error) => Err(error.to_string()),
+            };
+            tracing::trace!("{} in {elapsed:.2?}", request.method());
+            self.add(request, &outcome, elapsed);
+        }
+    }
+
+    fn add(
+        &self,
+        request: &SerializedRequest,
+        outcome: &Result<String, String>,
+        elapsed: Duration,
+    ) {
+        let mut summary = self.summary.lock().unwrap();
+        let stats = summary
+            .methods
+            .entry(request.method().to_string())
+            .or_default();
+        stats.calls += 1;
+        stats.errors += outcome.is_err() as u64;
+        stats.total_time += elapsed;
+        stats.max_time = stats.max_time.max(elapsed);
+
+        if self.record {
+            summary.records.push(RpcRecord {
+                method: request.method().to_string(),
+                params: request.params().map(|params| params.get().to_string()),
+                response: outcome.clone(),
+                elapsed,
+            });
+        }
+        drop(summary);
+
+        if let Some(parent) = &self.parent {
+            parent.add(request, outcome, elapsed);
+        }
+    }
+}
+
+impl<S> Layer<S> for RpcStats {
+    type Service = RpcStatsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcStatsService {
+            inner,
+            stats: self.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcStatsService<S> {
+    inner: S,
+    stats: RpcStats,
+}
+
+impl<S> Service<RequestPacket> for RpcStatsService<S>
+where
+    S: Service<
+            RequestPacket,
+            Response = ResponsePacket,
+            Error = TransportError,
+            Future = TransportFut<'static>,
+        >,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let stats = self.stats.clone();
+        let start = Instant::now();
+        let future = self.inner.call(request.clone());
+        Box::pin(async move {
+            let response = future.await;
+            stats.finish(&request, &response, start.elapsed());
+            response
+        })
+    }
+}
+
+/// HTTP provider with every request going through `stats`.
+pub fn provider_with_stats(rpc_url: Url, stats: RpcStats) -> RevmProvider {
+    let client = ClientBuilder::default().layer(stats).http(rpc_url);
+    Arc::new(ProviderBuilder::new().connect_client(client))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::rpc::json_rpc::{Request, Response};
+    use serde_json::value::RawValue;
+
+    use super::*;
+
+    fn get_code(stats: &RpcStats, id: u64) {
+        let request = Request::new("eth_getCode", Id::Number(id), ())
+            .serialize()
+            .unwrap();
+        let response = Response {
+            id: Id::Number(id),
+            payload: ResponsePayload::Success(RawValue::from_string("\"0x\"".to_string()).unwrap()),
+        };
+        stats.finish(
+            &RequestPacket::Single(request),
+            &Ok(ResponsePacket::Single(response)),
+            Duration::from_millis(id),
+        );
+    }
+
+    #[test]
+    fn counts_requests_by_method() {
+        let stats = RpcStats::recording();
+        get_code(&stats, 1);
+        get_code(&stats, 3);
+
+        let summary = stats.summary();
+        assert_eq!(summary.count("eth_getCode"), 2);
+        assert_eq!(summary.count("eth_call"), 0);
+        assert_eq!(
+            summary.methods["eth_getCode"].max_time,
+            Duration::from_millis(3)
+        );
+        assert_eq!(summary.records[0].response, Ok("\"0x\"".to_string()));
+    }
+
+    #[test]
+    fn scoped_stats_add_to_parent() {
+        let stats = RpcStats::recording();
+        let scoped = stats.scoped();
+        get_code(&scoped, 1);
+        assert_eq!(scoped.reset().total(), 1);
+        get_code(&scoped, 2);
+
+        assert_eq!(scoped.total(), 1);
+        assert_eq!(stats.total(), 2);
+        assert_eq!(stats.summary().records.len(), 2);
+    }
+}
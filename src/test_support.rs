@@ -0,0 +1,338 @@
+//! Hand-assembled contracts for tests that run without a compiler.
+//!
+//! Shared with the integration tests through `#[path]`, so nothing here
+//! depends on the crate itself.
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use alloy::primitives::{Address, Bytes};
+use revm::{
+    database::CacheDB,
+    state::{AccountInfo, Bytecode},
+};
+
+pub mod op {
+    pub const STOP: u8 = 0x00;
+    pub const ADD: u8 = 0x01;
+    pub const MUL: u8 = 0x02;
+    pub const SUB: u8 = 0x03;
+    pub const DIV: u8 = 0x04;
+    pub const LT: u8 = 0x10;
+    pub const SGT: u8 = 0x13;
+    pub const EQ: u8 = 0x14;
+    pub const ISZERO: u8 = 0x15;
+    pub const SHL: u8 = 0x1b;
+    pub const SHR: u8 = 0x1c;
+    pub const SHA3: u8 = 0x20;
+    pub const ADDRESS: u8 = 0x30;
+    pub const CALLER: u8 = 0x33;
+    pub const CALLDATALOAD: u8 = 0x35;
+    pub const CODECOPY: u8 = 0x39;
+    pub const RETURNDATASIZE: u8 = 0x3d;
+    pub const RETURNDATACOPY: u8 = 0x3e;
+    pub const POP: u8 = 0x50;
+    pub const MLOAD: u8 = 0x51;
+    pub const MSTORE: u8 = 0x52;
+    pub const SLOAD: u8 = 0x54;
+    pub const SSTORE: u8 = 0x55;
+    pub const JUMPI: u8 = 0x57;
+    pub const GAS: u8 = 0x5a;
+    pub const JUMPDEST: u8 = 0x5b;
+    pub const PUSH1: u8 = 0x60;
+    pub const PUSH2: u8 = 0x61;
+    pub const PUSH4: u8 = 0x63;
+    pub const DUP1: u8 = 0x80;
+    pub const DUP3: u8 = 0x82;
+    pub const DUP6: u8 = 0x85;
+    pub const SWAP1: u8 = 0x90;
+    pub const CALL: u8 = 0xf1;
+    pub const RETURN: u8 = 0xf3;
+    pub const REVERT: u8 = 0xfd;
+    pub const INVALID: u8 = 0xfe;
+}
+
+use op::*;
+
+pub const BALANCE_OF: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
+pub const ALLOWANCE: [u8; 4] = [0xdd, 0x62, 0xed, 0x3e];
+pub const TRANSFER: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb];
+pub const SWAP: [u8; 4] = [0x12, 0x8a, 0xcb, 0x08];
+pub const SWAP_CALLBACK: [u8; 4] = [0xfa, 0x46, 0x1e, 0x33];
+pub const QUOTE_EXACT_INPUT_SINGLE: [u8; 4] = [0xc6, 0xa5, 0x02, 0x6a];
+
+/// Order in which a mapping lookup hashes the key and the slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashOrder {
+    /// `keccak256(key . slot)`, Solidity.
+    KeyFirst,
+    /// `keccak256(slot . key)`, Vyper.
+    SlotFirst,
+}
+
+/// Just enough of an assembler for the mocks, jump targets are resolved when finished.
+#[derive(Default)]
+pub struct Asm {
+    code: Vec<u8>,
+    labels: HashMap<&'static str, u16>,
+    jumps: Vec<(usize, &'static str)>,
+}
+
+impl Asm {
+    pub fn ops(&mut self, ops: &[u8]) -> &mut Self {
+        self.code.extend_from_slice(ops);
+        self
+    }
+
+    pub fn push(&mut self, value: u16) -> &mut Self {
+        match u8::try_from(value) {
+            Ok(value) => self.ops(&[PUSH1, value]),
+            Err(_) => self.ops(&[PUSH2, (value >> 8) as u8, value as u8]),
+        }
+    }
+
+    /// Pushes the selector shifted to the top 4 bytes of a word, as at the start of calldata.
+    pub fn push_selector(&mut self, selector: [u8; 4]) -> &mut Self {
+        self.ops(&[PUSH4]).ops(&selector).ops(&[PUSH1, 0xe0, SHL])
+    }
+
+    pub fn load_selector(&mut self) -> &mut Self {
+        self.ops(&[PUSH1, 0x00, CALLDATALOAD, PUSH1, 0xe0, SHR])
+    }
+
+    pub fn push_label(&mut self, label: &'static str) -> &mut Self {
+        self.jumps.push((self.code.len() + 1, label));
+        self.ops(&[PUSH2, 0x00, 0x00])
+    }
+
+    pub fn jumpi(&mut self, label: &'static str) -> &mut Self {
+        self.push_label(label).ops(&[JUMPI])
+    }
+
+    /// Jumps to `label` if the selector left on the stack is `selector`.
+    pub fn dispatch(&mut self, selector: [u8; 4], label: &'static str) -> &mut Self {
+        self.ops(&[DUP1, PUSH4])
+            .ops(&selector)
+            .ops(&[EQ])
+            .jumpi(label)
+    }
+
+    pub fn label(&mut self, label: &'static str) -> &mut Self {
+        self.labels.insert(label, self.code.len() as u16);
+        self.ops(&[JUMPDEST])
+    }
+
+    /// Returns the word on the stack.
+    pub fn return_word(&mut self) -> &mut Self {
+        self.ops(&[PUSH1, 0x00, MSTORE, PUSH1, 0x20, PUSH1, 0x00, RETURN])
+    }
+
+    pub fn revert_empty(&mut self) -> &mut Self {
+        self.ops(&[PUSH1, 0x00, DUP1, REVERT])
+    }
+
+    /// Reverts with the return data of the last call.
+    pub fn bubble_revert(&mut self) -> &mut Self {
+        self.ops(&[RETURNDATASIZE, PUSH1, 0x00, PUSH1, 0x00, RETURNDATACOPY])
+            .ops(&[RETURNDATASIZE, PUSH1, 0x00, REVERT])
+    }
+
+    /// Replaces the slot and the key on top of it with the slot of `mapping[key]`.
+    pub fn hash_mapping(&mut self, order: HashOrder) -> &mut Self {
+        let (key_offset, slot_offset) = match order {
+            HashOrder::KeyFirst => (0x00, 0x20),
+            HashOrder::SlotFirst => (0x20, 0x00),
+        };
+        self.ops(&[PUSH1, key_offset, MSTORE, PUSH1, slot_offset, MSTORE])
+            .ops(&[PUSH1, 0x40, PUSH1, 0x00, SHA3])
+    }
+
+    /// Replaces the key on the stack with its slot in the mapping at `slot`.
+    pub fn mapping_slot(&mut self, order: HashOrder, slot: u8) -> &mut Self {
+        self.ops(&[PUSH1, slot, SWAP1]).hash_mapping(order)
+    }
+
+    pub fn finish(&mut self) -> Bytes {
+        for (offset, label) in &self.jumps {
+            let target = self.labels[label].to_be_bytes();
+            self.code[*offset..*offset + 2].copy_from_slice(&target);
+        }
+        Bytes::from(self.code.clone())
+    }
+}
+
+/// `balanceOf`, `allowance` and `transfer` over the balances mapping at
+/// `balance_slot` and the allowances mapping at `allowance_slot`.
+pub fn token_code(order: HashOrder, balance_slot: u8, allowance_slot: u8) -> Bytes {
+    let mut asm = Asm::default();
+    asm.load_selector()
+        .dispatch(BALANCE_OF, "balance_of")
+        .dispatch(ALLOWANCE, "allowance")
+        .dispatch(TRANSFER, "transfer")
+        .revert_empty();
+
+    asm.label("balance_of")
+        .ops(&[PUSH1, 0x04, CALLDATALOAD])
+        .mapping_slot(order, balance_slot)
+        .ops(&[SLOAD])
+        .return_word();
+
+    // The inner mapping lives at the slot of the owner, keyed by the spender.
+    asm.label("allowance")
+        .ops(&[PUSH1, 0x04, CALLDATALOAD])
+        .mapping_slot(order, allowance_slot)
+        .ops(&[PUSH1, 0x24, CALLDATALOAD])
+        .hash_mapping(order)
+        .ops(&[SLOAD])
+        .return_word();
+
+    // [from slot, balance, amount], reverts if the balance is too low.
+    asm.label("transfer")
+        .ops(&[CALLER])
+        .mapping_slot(order, balance_slot)
+        .ops(&[DUP1, SLOAD, PUSH1, 0x24, CALLDATALOAD, DUP1, DUP3, LT])
+        .jumpi("insufficient")
+        .ops(&[SWAP1, SUB, SWAP1, SSTORE])
+        .ops(&[PUSH1, 0x04, CALLDATALOAD])
+        .mapping_slot(order, balance_slot)
+        .ops(&[DUP1, SLOAD, PUSH1, 0x24, CALLDATALOAD, ADD, SWAP1, SSTORE])
+        .ops(&[PUSH1, 0x01])
+        .return_word();
+
+    asm.label("insufficient").revert_empty();
+    asm.finish()
+}
+
+/// Exact-input `swap` at a constant price with a 0.3% fee, token0 and token1
+/// in slots 0 and 1. Pays the amount out to the recipient, then calls
+/// `uniswapV3SwapCallback` on the caller and returns the deltas.
+pub fn pool_code() -> Bytes {
+    let mut asm = Asm::default();
+    asm.load_selector().dispatch(SWAP, "swap").revert_empty();
+
+    asm.label("swap")
+        .ops(&[PUSH1, 0x00, PUSH1, 0x44, CALLDATALOAD, SGT, ISZERO])
+        .jumpi("zero_amount");
+    // amount out = amount in * 997 / 1000, kept at 0x100.
+    asm.push(1000)
+        .push(997)
+        .ops(&[PUSH1, 0x44, CALLDATALOAD, MUL, DIV])
+        .push(0x100)
+        .ops(&[MSTORE]);
+
+    // token out (slot 1 for zeroForOne) .transfer(recipient, amount out)
+    asm.ops(&[PUSH1, 0x24, CALLDATALOAD, SLOAD])
+        .push_selector(TRANSFER)
+        .push(0x120)
+        .ops(&[MSTORE, PUSH1, 0x04, CALLDATALOAD])
+        .push(0x124)
+        .ops(&[MSTORE])
+        .push(0x100)
+        .ops(&[MLOAD])
+        .push(0x144)
+        .ops(&[MSTORE]);
+    asm.ops(&[PUSH1, 0x20, PUSH1, 0x00, PUSH1, 0x44])
+        .push(0x120)
+        .ops(&[PUSH1, 0x00, DUP6, GAS, CALL, ISZERO])
+        .jumpi("bubble")
+        .ops(&[POP]);
+
+    // uniswapV3SwapCallback(amount0, amount1, ""), the amount in at 0x124
+    // for zeroForOne and at 0x144 otherwise, minus the amount out at the other one.
+    asm.push_selector(SWAP_CALLBACK).push(0x120).ops(&[MSTORE]);
+    asm.push(0x100)
+        .ops(&[MLOAD, PUSH1, 0x00, SUB, PUSH1, 0x44, CALLDATALOAD])
+        .ops(&[PUSH1, 0x24, CALLDATALOAD, PUSH1, 0x20, MUL])
+        .push(0x144)
+        .ops(&[SUB, MSTORE])
+        .ops(&[PUSH1, 0x24, CALLDATALOAD, PUSH1, 0x20, MUL])
+        .push(0x124)
+        .ops(&[ADD, MSTORE]);
+    asm.ops(&[PUSH1, 0x60])
+        .push(0x164)
+        .ops(&[MSTORE, PUSH1, 0x00])
+        .push(0x184)
+        .ops(&[MSTORE]);
+    asm.ops(&[PUSH1, 0x00, PUSH1, 0x00, PUSH1, 0x84])
+        .push(0x120)
+        .ops(&[PUSH1, 0x00, CALLER, GAS, CALL, ISZERO])
+        .jumpi("bubble");
+    asm.ops(&[PUSH1, 0x40]).push(0x124).ops(&[RETURN]);
+
+    asm.label("zero_amount").revert_empty();
+    asm.label("bubble").bubble_revert();
+    asm.finish()
+}
+
+/// Quoter V2 `quoteExactInputSingle` through the pool in slot 0: swaps, takes the
+/// amount out from the callback revert and returns it with the gas of the swap,
+/// a price of 1 and no ticks crossed.
+pub fn quoter_code() -> Bytes {
+    let mut asm = Asm::default();
+    asm.load_selector()
+        .dispatch(QUOTE_EXACT_INPUT_SINGLE, "quote")
+        .dispatch(SWAP_CALLBACK, "callback")
+        .revert_empty();
+
+    // Reverts with the negated negative delta.
+    asm.label("callback")
+        .ops(&[PUSH1, 0x04, CALLDATALOAD, PUSH1, 0x00, SGT])
+        .ops(&[PUSH1, 0x20, MUL, PUSH1, 0x24, SUB, CALLDATALOAD])
+        .ops(&[PUSH1, 0x00, SUB])
+        .ops(&[PUSH1, 0x00, MSTORE, PUSH1, 0x20, PUSH1, 0x00, REVERT]);
+
+    // swap(this, tokenIn < tokenOut, amountIn, sqrtPriceLimitX96, "")
+    asm.label("quote")
+        .push_selector(SWAP)
+        .push(0x100)
+        .ops(&[MSTORE, ADDRESS])
+        .push(0x104)
+        .ops(&[MSTORE])
+        .ops(&[PUSH1, 0x24, CALLDATALOAD, PUSH1, 0x04, CALLDATALOAD, LT])
+        .push(0x124)
+        .ops(&[MSTORE, PUSH1, 0x44, CALLDATALOAD])
+        .push(0x144)
+        .ops(&[MSTORE, PUSH1, 0x84, CALLDATALOAD])
+        .push(0x164)
+        .ops(&[MSTORE, PUSH1, 0xa0])
+        .push(0x184)
+        .ops(&[MSTORE, PUSH1, 0x00])
+        .push(0x1a4)
+        .ops(&[MSTORE]);
+    asm.ops(&[GAS, PUSH1, 0x00, PUSH1, 0x00, PUSH1, 0xc4])
+        .push(0x100)
+        .ops(&[PUSH1, 0x00, PUSH1, 0x00, SLOAD, GAS, CALL, GAS, SWAP1])
+        .jumpi("unexpected");
+    // Anything but the amount out is a failed swap.
+    asm.ops(&[RETURNDATASIZE, PUSH1, 0x20, EQ, ISZERO])
+        .jumpi("bubble");
+    asm.ops(&[SWAP1, SUB, PUSH1, 0x60, MSTORE])
+        .ops(&[PUSH1, 0x20, PUSH1, 0x00, PUSH1, 0x00, RETURNDATACOPY])
+        .ops(&[PUSH1, 0x01, PUSH1, 0x60, SHL, PUSH1, 0x20, MSTORE])
+        .ops(&[PUSH1, 0x00, PUSH1, 0x40, MSTORE])
+        .ops(&[PUSH1, 0x80, PUSH1, 0x00, RETURN]);
+
+    asm.label("unexpected").revert_empty();
+    asm.label("bubble").bubble_revert();
+    asm.finish()
+}
+
+/// Creation code that deploys `runtime` as is.
+pub fn init_code(runtime: &Bytes) -> Bytes {
+    let length = (runtime.len() as u16).to_be_bytes();
+    let mut code = vec![PUSH2, length[0], length[1], DUP1, PUSH1, 0x0d, PUSH1, 0x00];
+    code.extend([CODECOPY, PUSH1, 0x00, RETURN, STOP]);
+    code.extend_from_slice(runtime);
+    Bytes::from(code)
+}
+
+pub fn insert_code<ExtDB>(db: &mut CacheDB<ExtDB>, address: Address, code: Bytes) {
+    let bytecode = Bytecode::new_raw(code);
+    let info = AccountInfo {
+        code_hash: bytecode.hash_slow(),
+        code: Some(bytecode),
+        ..Default::default()
+    };
+    db.insert_account_info(address, info);
+}
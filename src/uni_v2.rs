@@ -0,0 +1,250 @@
+//! Uniswap V2-style constant-product pairs (Uniswap V2, SushiSwap and other forks).
+
+use alloy::primitives::{Address, U256};
+use anyhow::{anyhow, ensure};
+use revm::Database;
+
+/// The part of the input kept after fees is `fee_numerator / FEE_DENOMINATOR`.
+pub const FEE_DENOMINATOR: u32 = 10_000;
+/// Uniswap V2 and SushiSwap keep 99.7% of the input (0.3% fee).
+pub const UNI_V2_FEE_NUMERATOR: u32 = 9_970;
+
+// Storage layout of `UniswapV2Pair`: `reserve0`, `reserve1` and
+// `blockTimestampLast` are packed into a single slot.
+const RESERVES_SLOT: u64 = 8;
+
+/// Returns the maximum output amount for `amount_in`, same as
+/// `UniswapV2Library.getAmountOut` with a configurable fee numerator.
+pub fn get_amount_out(
+    amount_in: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    fee_numerator: u32,
+) -> anyhow::Result<U256> {
+    ensure!(amount_in > U256::ZERO, "insufficient input amount");
+    ensure!(
+        fee_numerator > 0 && fee_numerator <= FEE_DENOMINATOR,
+        "invalid fee numerator {fee_numerator}"
+    );
+    ensure!(
+        reserve_in > U256::ZERO && reserve_out > U256::ZERO,
+        "insufficient liquidity"
+    );
+
+    let amount_in_with_fee = amount_in
+        .checked_mul(U256::from(fee_numerator))
+        .ok_or_else(|| anyhow!("amount in overflow"))?;
+    let numerator = amount_in_with_fee
+        .checked_mul(reserve_out)
+        .ok_or_else(|| anyhow!("amount in overflow"))?;
+    let denominator = reserve_in
+        .checked_mul(U256::from(FEE_DENOMINATOR))
+        .and_then(|reserve_in| reserve_in.checked_add(amount_in_with_fee))
+        .ok_or_else(|| anyhow!("reserve in overflow"))?;
+    Ok(numerator / denominator)
+}
+
+/// Returns the minimum input amount required to receive `amount_out`, same as
+/// `UniswapV2Library.getAmountIn` with a configurable fee numerator.
+pub fn get_amount_in(
+    amount_out: U256,
+    reserve_in: U256,
+    reserve_out: U256,
+    fee_numerator: u32,
+) -> anyhow::Result<U256> {
+    ensure!(amount_out > U256::ZERO, "insufficient output amount");
+    ensure!(
+        fee_numerator > 0 && fee_numerator <= FEE_DENOMINATOR,
+        "invalid fee numerator {fee_numerator}"
+    );
+    ensure!(
+        reserve_in > U256::ZERO && amount_out < reserve_out,
+        "insufficient liquidity"
+    );
+
+    let numerator = reserve_in
+        .checked_mul(amount_out)
+        .and_then(|numerator| numerator.checked_mul(U256::from(FEE_DENOMINATOR)))
+        .ok_or_else(|| anyhow!("amount out overflow"))?;
+    let denominator = (reserve_out - amount_out)
+        .checked_mul(U256::from(fee_numerator))
+        .ok_or_else(|| anyhow!("reserve out overflow"))?;
+    Ok(numerator / denominator + U256::from(1))
+}
+
+/// Splits the reserves slot into `reserve0`, `reserve1` and `blockTimestampLast`.
+pub fn unpack_reserves(reserves: U256) -> (U256, U256, u32) {
+    let mask = (U256::from(1) << 112) - U256::from(1);
+    let reserve0 = reserves & mask;
+    let reserve1 = (reserves >> 112_usize) & mask;
+    let block_timestamp_last = (reserves >> 224_usize).to::<u32>();
+    (reserve0, reserve1, block_timestamp_last)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniV2Pair {
+    pub address: Address,
+    pub reserve0: U256,
+    pub reserve1: U256,
+    /// Part of the input kept after fees, over [`FEE_DENOMINATOR`].
+    pub fee_numerator: u32,
+}
+
+impl UniV2Pair {
+    pub fn new(address: Address, reserve0: U256, reserve1: U256, fee_numerator: u32) -> Self {
+        Self {
+            address,
+            reserve0,
+            reserve1,
+            fee_numerator,
+        }
+    }
+
+    /// Loads the reserves from the pair storage.
+    pub fn load<DB>(db: &mut DB, address: Address, fee_numerator: u32) -> anyhow::Result<Self>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let reserves = db.storage(address, U256::from(RESERVES_SLOT))?;
+        let (reserve0, reserve1, _) = unpack_reserves(reserves);
+        Ok(Self::new(address, reserve0, reserve1, fee_numerator))
+    }
+
+    fn reserves(&self, zero_for_one: bool) -> (U256, U256) {
+        if zero_for_one {
+            (self.reserve0, self.reserve1)
+        } else {
+            (self.reserve1, self.reserve0)
+        }
+    }
+
+    /// Returns the amount of the other token received for `amount_in`.
+    pub fn exact_input(&self, zero_for_one: bool, amount_in: U256) -> anyhow::Result<U256> {
+        let (reserve_in, reserve_out) = self.reserves(zero_for_one);
+        get_amount_out(amount_in, reserve_in, reserve_out, self.fee_numerator)
+    }
+
+    /// Returns the amount of the other token required to receive `amount_out`.
+    pub fn exact_output(&self, zero_for_one: bool, amount_out: U256) -> anyhow::Result<U256> {
+        let (reserve_in, reserve_out) = self.reserves(zero_for_one);
+        get_amount_in(amount_out, reserve_in, reserve_out, self.fee_numerator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use revm::database::{CacheDB, EmptyDB};
+
+    use super::*;
+
+    const ONE: u128 = 1_000_000_000_000_000_000;
+
+    fn ether(amount: u128) -> U256 {
+        U256::from(amount * ONE)
+    }
+
+    #[test]
+    fn amount_out_matches_uniswap_v2() {
+        // `getInputPrice` cases of the Uniswap V2 pair tests: (in, reserve in, reserve out).
+        for (amount_in, reserve_in, reserve_out, expected) in [
+            (1, 5, 10, 1662497915624478906_u128),
+            (1, 10, 5, 453305446940074565),
+            (2, 5, 10, 2851015155847869602),
+            (2, 10, 5, 831248957812239453),
+            (1, 10, 10, 906610893880149131),
+            (1, 100, 100, 987158034397061298),
+            (1, 1000, 1000, 996006981039903216),
+        ] {
+            let amount_out = get_amount_out(
+                ether(amount_in),
+                ether(reserve_in),
+                ether(reserve_out),
+                UNI_V2_FEE_NUMERATOR,
+            )
+            .unwrap();
+            assert_eq!(amount_out, U256::from(expected));
+        }
+    }
+
+    #[test]
+    fn amount_in_rounds_up() {
+        let (reserve_in, reserve_out) = (U256::from(100), U256::from(100));
+        assert_eq!(
+            get_amount_out(U256::from(2), reserve_in, reserve_out, UNI_V2_FEE_NUMERATOR).unwrap(),
+            U256::from(1)
+        );
+        assert_eq!(
+            get_amount_in(U256::from(1), reserve_in, reserve_out, UNI_V2_FEE_NUMERATOR).unwrap(),
+            U256::from(2)
+        );
+        // Inverts `get_amount_out` on the same reserves.
+        assert_eq!(
+            get_amount_in(
+                U256::from(1662497915624478906_u128),
+                ether(5),
+                ether(10),
+                UNI_V2_FEE_NUMERATOR
+            )
+            .unwrap(),
+            ether(1)
+        );
+    }
+
+    #[test]
+    fn rejects_empty_reserves_and_amounts() {
+        let fee = UNI_V2_FEE_NUMERATOR;
+        assert!(get_amount_out(ether(1), U256::ZERO, ether(10), fee).is_err());
+        assert!(get_amount_out(ether(1), ether(10), U256::ZERO, fee).is_err());
+        assert!(get_amount_out(U256::ZERO, ether(10), ether(10), fee).is_err());
+        assert!(get_amount_in(ether(1), U256::ZERO, ether(10), fee).is_err());
+        assert!(get_amount_in(ether(10), ether(10), ether(10), fee).is_err());
+        assert!(get_amount_in(U256::ZERO, ether(10), ether(10), fee).is_err());
+    }
+
+    #[test]
+    fn fails_on_overflow() {
+        let fee = UNI_V2_FEE_NUMERATOR;
+        let half = U256::MAX / U256::from(2);
+        assert!(get_amount_in(half, half, U256::MAX, fee).is_err());
+        assert!(get_amount_in(ether(1), ether(1), U256::MAX, fee).is_err());
+        assert!(get_amount_out(ether(1), U256::MAX, ether(1), fee).is_err());
+        assert!(get_amount_out(half, ether(1), ether(1), fee).is_err());
+    }
+
+    #[test]
+    fn rejects_zero_fee_numerator() {
+        let (amount, reserve) = (ether(1), ether(100));
+        assert!(get_amount_out(amount, reserve, reserve, 0).is_err());
+        assert!(get_amount_in(amount, reserve, reserve, 0).is_err());
+    }
+
+    #[test]
+    fn rejects_fee_numerator_above_denominator() {
+        let (amount, reserve) = (ether(1), ether(100));
+        for fee in [FEE_DENOMINATOR + 1, u32::MAX] {
+            assert!(get_amount_out(amount, reserve, reserve, fee).is_err());
+            assert!(get_amount_in(amount, reserve, reserve, fee).is_err());
+        }
+        // No fee at all is still a valid pair.
+        assert!(get_amount_out(amount, reserve, reserve, FEE_DENOMINATOR).unwrap() < amount);
+        assert!(get_amount_in(amount, reserve, reserve, FEE_DENOMINATOR).unwrap() > amount);
+    }
+
+    #[test]
+    fn loads_packed_reserves() {
+        let reserve0 = U256::from(5_000_000_000_000_u64);
+        let reserve1 = ether(2_000);
+        let timestamp = 1_700_000_000_u32;
+        let packed = reserve0 | (reserve1 << 112_usize) | (U256::from(timestamp) << 224_usize);
+
+        assert_eq!(unpack_reserves(packed), (reserve0, reserve1, timestamp));
+
+        let address = Address::repeat_byte(0x11);
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_storage(address, U256::from(RESERVES_SLOT), packed)
+            .unwrap();
+        let pair = UniV2Pair::load(&mut db, address, UNI_V2_FEE_NUMERATOR).unwrap();
+        assert_eq!((pair.reserve0, pair.reserve1), (reserve0, reserve1));
+    }
+}
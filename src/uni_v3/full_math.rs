@@ -0,0 +1,34 @@
+use alloy::primitives::{U256, U512};
+use anyhow::{anyhow, ensure};
+
+/// Calculates `floor(a * b / denominator)` with full precision,
+/// fails if the result overflows 256 bits or `denominator == 0`.
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> anyhow::Result<U256> {
+    ensure!(!denominator.is_zero(), "mul_div: zero denominator");
+    let result = U512::from(a) * U512::from(b) / U512::from(denominator);
+    ensure!(result <= U512::from(U256::MAX), "mul_div: overflow");
+    Ok(U256::from_limbs_slice(&result.as_limbs()[..4]))
+}
+
+/// Calculates `ceil(a * b / denominator)` with full precision,
+/// fails if the result overflows 256 bits or `denominator == 0`.
+pub fn mul_div_rounding_up(a: U256, b: U256, denominator: U256) -> anyhow::Result<U256> {
+    let result = mul_div(a, b, denominator)?;
+    if a.mul_mod(b, denominator).is_zero() {
+        Ok(result)
+    } else {
+        result
+            .checked_add(U256::from(1))
+            .ok_or_else(|| anyhow!("mul_div_rounding_up: overflow"))
+    }
+}
+
+/// Returns `ceil(x / y)`, `y` must be non-zero.
+pub fn div_rounding_up(x: U256, y: U256) -> U256 {
+    let (quotient, remainder) = x.div_rem(y);
+    if remainder.is_zero() {
+        quotient
+    } else {
+        quotient + U256::from(1)
+    }
+}
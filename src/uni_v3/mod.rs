@@ -0,0 +1,12 @@
+//! Native port of the Uniswap V3 core math, quotes without the EVM.
+
+pub mod full_math;
+pub mod path;
+pub mod pool;
+pub mod sqrt_price_math;
+pub mod swap_math;
+pub mod tick_bitmap;
+pub mod tick_math;
+
+pub use path::{V3Path, V3PathBuilder};
+pub use pool::{SwapResult, UniV3Pool, load_slot0, slippage_sqrt_price_limit, tick_spacing};
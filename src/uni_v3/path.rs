@@ -0,0 +1,219 @@
+use alloy::primitives::{Address, Bytes};
+use anyhow::{anyhow, ensure};
+
+use super::tick_spacing;
+
+const ADDR_SIZE: usize = 20;
+const FEE_SIZE: usize = 3;
+const HOP_SIZE: usize = FEE_SIZE + ADDR_SIZE;
+
+/// Multi-hop swap route, packed as `token0 . fee0 . token1 . fee1 . token2 ...`
+/// for the router and the quoter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct V3Path {
+    tokens: Vec<Address>,
+    fees: Vec<u32>,
+}
+
+/// Builds a [`V3Path`] hop by hop, starting with the token in.
+#[derive(Debug, Clone)]
+pub struct V3PathBuilder {
+    tokens: Vec<Address>,
+    fees: Vec<u32>,
+}
+
+impl V3PathBuilder {
+    /// Swaps the last token for `token_out` through the pool with the `fee` tier.
+    pub fn hop(mut self, fee: u32, token_out: Address) -> Self {
+        self.fees.push(fee);
+        self.tokens.push(token_out);
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<V3Path> {
+        V3Path::new(self.tokens, self.fees)
+    }
+}
+
+impl V3Path {
+    /// `fees` has one fee per pool, so one less than `tokens`.
+    pub fn new(tokens: Vec<Address>, fees: Vec<u32>) -> anyhow::Result<Self> {
+        ensure!(!fees.is_empty(), "path has no pools");
+        ensure!(
+            fees.len() + 1 == tokens.len(),
+            "path has {} tokens for {} pools",
+            tokens.len(),
+            fees.len()
+        );
+        for (fee, pair) in fees.iter().zip(tokens.windows(2)) {
+            ensure!(
+                tick_spacing(*fee).is_some(),
+                "unknown fee tier {fee} for {} -> {}",
+                pair[0],
+                pair[1]
+            );
+            ensure!(pair[0] != pair[1], "pool swaps {} for itself", pair[0]);
+        }
+        Ok(Self { tokens, fees })
+    }
+
+    pub fn builder(token_in: Address) -> V3PathBuilder {
+        V3PathBuilder {
+            tokens: vec![token_in],
+            fees: Vec::new(),
+        }
+    }
+
+    /// Decodes a packed path, see [`V3Path::encode`].
+    pub fn decode(path: &[u8]) -> anyhow::Result<Self> {
+        let hops = path
+            .len()
+            .checked_sub(ADDR_SIZE)
+            .filter(|hops| hops % HOP_SIZE == 0)
+            .map(|hops| hops / HOP_SIZE)
+            .ok_or_else(|| anyhow!("invalid packed path length {}", path.len()))?;
+
+        let mut tokens = vec![Address::from_slice(&path[..ADDR_SIZE])];
+        let mut fees = Vec::with_capacity(hops);
+        for hop in path[ADDR_SIZE..].chunks_exact(HOP_SIZE) {
+            fees.push(u32::from_be_bytes([0, hop[0], hop[1], hop[2]]));
+            tokens.push(Address::from_slice(&hop[FEE_SIZE..]));
+        }
+        Self::new(tokens, fees)
+    }
+
+    pub fn encode(&self) -> Bytes {
+        let mut path = Vec::with_capacity(ADDR_SIZE + self.fees.len() * HOP_SIZE);
+        path.extend_from_slice(self.tokens[0].as_slice());
+        for (fee, token) in self.fees.iter().zip(&self.tokens[1..]) {
+            path.extend_from_slice(&fee.to_be_bytes()[1..]);
+            path.extend_from_slice(token.as_slice());
+        }
+        Bytes::from(path)
+    }
+
+    /// The same route from the token out to the token in,
+    /// the order exact-output swaps and quotes expect.
+    pub fn reversed(&self) -> Self {
+        Self {
+            tokens: self.tokens.iter().rev().copied().collect(),
+            fees: self.fees.iter().rev().copied().collect(),
+        }
+    }
+
+    pub fn tokens(&self) -> &[Address] {
+        &self.tokens
+    }
+
+    pub fn fees(&self) -> &[u32] {
+        &self.fees
+    }
+
+    pub fn token_in(&self) -> Address {
+        self.tokens[0]
+    }
+
+    pub fn token_out(&self) -> Address {
+        self.tokens[self.tokens.len() - 1]
+    }
+
+    /// `(token_in, fee, token_out)` of every pool in swap order.
+    pub fn pools(&self) -> impl Iterator<Item = (Address, u32, Address)> + '_ {
+        self.fees
+            .iter()
+            .zip(self.tokens.windows(2))
+            .map(|(fee, pair)| (pair[0], *fee, pair[1]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::hex;
+
+    use super::*;
+    use crate::address::{DAI_ADDR, USDC_ADDR, WETH_ADDR};
+
+    fn usdc_weth_dai() -> V3Path {
+        V3Path::builder(USDC_ADDR)
+            .hop(500, WETH_ADDR)
+            .hop(3000, DAI_ADDR)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn encodes_like_solidity_packed() {
+        // abi.encodePacked(USDC, uint24(500), WETH, uint24(3000), DAI)
+        let expected = hex!(
+            "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"
+            "0001f4"
+            "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"
+            "000bb8"
+            "6b175474e89094c44da98b954eedeac495271d0f"
+        );
+        assert_eq!(usdc_weth_dai().encode(), Bytes::copy_from_slice(&expected));
+    }
+
+    #[test]
+    fn fees_take_three_bytes() {
+        let path = V3Path::builder(WETH_ADDR)
+            .hop(10000, USDC_ADDR)
+            .build()
+            .unwrap();
+        assert_eq!(&path.encode()[20..23], &[0x00, 0x27, 0x10]);
+
+        let path = V3Path::builder(WETH_ADDR)
+            .hop(100, USDC_ADDR)
+            .build()
+            .unwrap();
+        assert_eq!(&path.encode()[20..23], &[0x00, 0x00, 0x64]);
+
+        // Fees that don't fit in a uint24 are not fee tiers.
+        assert!(V3Path::new(vec![WETH_ADDR, USDC_ADDR], vec![1 << 24]).is_err());
+        let mut packed = path.encode().to_vec();
+        packed[20..23].copy_from_slice(&[0xff, 0xff, 0xff]);
+        assert!(V3Path::decode(&packed).is_err());
+    }
+
+    #[test]
+    fn round_trips() {
+        let path = usdc_weth_dai();
+        assert_eq!(V3Path::decode(&path.encode()).unwrap(), path);
+        assert_eq!(path.tokens(), &[USDC_ADDR, WETH_ADDR, DAI_ADDR]);
+        assert_eq!(path.fees(), &[500, 3000]);
+        assert_eq!(
+            path.pools().collect::<Vec<_>>(),
+            vec![(USDC_ADDR, 500, WETH_ADDR), (WETH_ADDR, 3000, DAI_ADDR)]
+        );
+    }
+
+    #[test]
+    fn reverses_tokens_and_fees() {
+        let reversed = usdc_weth_dai().reversed();
+        assert_eq!(reversed.token_in(), DAI_ADDR);
+        assert_eq!(reversed.token_out(), USDC_ADDR);
+        assert_eq!(reversed.fees(), &[3000, 500]);
+        assert_eq!(reversed.reversed(), usdc_weth_dai());
+    }
+
+    #[test]
+    fn rejects_paths_without_pools() {
+        assert!(V3Path::builder(WETH_ADDR).build().is_err());
+        assert!(V3Path::new(Vec::new(), Vec::new()).is_err());
+        assert!(V3Path::decode(&[]).is_err());
+        assert!(V3Path::decode(WETH_ADDR.as_slice()).is_err());
+        // A token and a fee without the token out.
+        assert!(V3Path::decode(&usdc_weth_dai().encode()[..23]).is_err());
+    }
+
+    #[test]
+    fn rejects_mismatched_hops() {
+        assert!(V3Path::new(vec![WETH_ADDR, USDC_ADDR], vec![500, 3000]).is_err());
+        assert!(
+            V3Path::builder(WETH_ADDR)
+                .hop(500, WETH_ADDR)
+                .build()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,492 @@
+use std::collections::HashMap;
+
+use alloy::{
+    primitives::{Address, I256, U256, U512, keccak256},
+    sol_types::SolValue,
+};
+use anyhow::{anyhow, ensure};
+use revm::Database;
+
+use super::{
+    swap_math::compute_swap_step,
+    tick_bitmap::{next_initialized_tick_within_one_word, position},
+    tick_math::{
+        MAX_SQRT_RATIO, MAX_TICK, MIN_SQRT_RATIO, MIN_TICK, get_sqrt_ratio_at_tick,
+        get_tick_at_sqrt_ratio,
+    },
+};
+
+// Storage layout of `UniswapV3Pool`.
+const SLOT0_SLOT: u64 = 0;
+const LIQUIDITY_SLOT: u64 = 4;
+const TICKS_SLOT: u64 = 5;
+const TICK_BITMAP_SLOT: u64 = 6;
+
+const BPS_DENOMINATOR: u32 = 10_000;
+
+/// Returns the tick spacing enabled by the factory for the fee tier.
+pub fn tick_spacing(fee: u32) -> Option<i32> {
+    match fee {
+        100 => Some(1),
+        500 => Some(10),
+        3000 => Some(60),
+        10000 => Some(200),
+        _ => None,
+    }
+}
+
+/// Reads `(sqrtPriceX96, tick)` from the pool's `slot0`.
+pub fn load_slot0<DB>(db: &mut DB, address: Address) -> anyhow::Result<(U256, i32)>
+where
+    DB: Database,
+    DB::Error: std::error::Error + Send + Sync + 'static,
+{
+    let slot0 = db.storage(address, U256::from(SLOT0_SLOT))?;
+    let sqrt_price_x96 = slot0 & ((U256::from(1) << 160) - U256::from(1));
+    // int24 tick right after the uint160 sqrtPriceX96.
+    let tick = ((slot0 >> 160_usize).as_limbs()[0] as u32 & 0xff_ffff) << 8;
+    let tick = (tick as i32) >> 8;
+    Ok((sqrt_price_x96, tick))
+}
+
+/// Returns the sqrt price limit that lets the price move at most `slippage_bps`
+/// away from `sqrt_price_x96`, clamped to the range the pool accepts.
+pub fn slippage_sqrt_price_limit(
+    sqrt_price_x96: U256,
+    zero_for_one: bool,
+    slippage_bps: u32,
+) -> U256 {
+    // Selling token0 lowers the price (token1 per token0), buying raises it.
+    let factor = if zero_for_one {
+        BPS_DENOMINATOR.saturating_sub(slippage_bps)
+    } else {
+        BPS_DENOMINATOR.saturating_add(slippage_bps)
+    };
+    let price = U512::from(sqrt_price_x96).pow(U512::from(2)) * U512::from(factor)
+        / U512::from(BPS_DENOMINATOR);
+    let limit = U256::saturating_from(price.root(2));
+    limit.clamp(
+        MIN_SQRT_RATIO + U256::from(1),
+        MAX_SQRT_RATIO - U256::from(1),
+    )
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SwapResult {
+    /// Pool's token0 delta, positive when the pool receives it.
+    pub amount0: I256,
+    /// Pool's token1 delta, positive when the pool receives it.
+    pub amount1: I256,
+    pub sqrt_price_x96_after: U256,
+    pub tick_after: i32,
+    pub liquidity_after: u128,
+    pub ticks_crossed: u32,
+}
+
+/// In-memory model of a Uniswap V3 pool, mirrors `UniswapV3Pool.swap`.
+///
+/// Only the bitmap words around the current tick are loaded,
+/// swaps moving the price beyond them fail.
+#[derive(Debug, Clone)]
+pub struct UniV3Pool {
+    pub address: Address,
+    pub fee: u32,
+    pub tick_spacing: i32,
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+    pub tick_bitmap: HashMap<i16, U256>,
+    /// `liquidityNet` of the initialized ticks.
+    pub ticks: HashMap<i32, i128>,
+}
+
+impl UniV3Pool {
+    /// Loads the pool state from storage, including `word_radius` bitmap words
+    /// on each side of the current tick and all the initialized ticks in them.
+    pub fn load<DB>(
+        db: &mut DB,
+        address: Address,
+        fee: u32,
+        word_radius: i16,
+    ) -> anyhow::Result<Self>
+    where
+        DB: Database,
+        DB::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let tick_spacing =
+            tick_spacing(fee).ok_or_else(|| anyhow!("unsupported fee tier {fee}"))?;
+
+        let (sqrt_price_x96, tick) = load_slot0(db, address)?;
+
+        // uint128 alone in its slot, anything in the upper bits is not a V3 pool.
+        let liquidity: u128 = db
+            .storage(address, U256::from(LIQUIDITY_SLOT))?
+            .try_into()
+            .map_err(|_| anyhow!("{address} is not a Uniswap V3 pool: invalid liquidity"))?;
+
+        let (current_word, _) = position(tick.div_euclid(tick_spacing));
+        let mut tick_bitmap = HashMap::new();
+        let mut ticks = HashMap::new();
+
+        let words =
+            current_word.saturating_sub(word_radius)..=current_word.saturating_add(word_radius);
+        for word_pos in words {
+            let word = db.storage(
+                address,
+                mapping_slot(I256::try_from(word_pos)?, TICK_BITMAP_SLOT),
+            )?;
+            tick_bitmap.insert(word_pos, word);
+
+            for bit_pos in 0..256 {
+                if !word.bit(bit_pos) {
+                    continue;
+                }
+                let tick = ((word_pos as i32) * 256 + bit_pos as i32) * tick_spacing;
+                let info = db.storage(address, mapping_slot(I256::try_from(tick)?, TICKS_SLOT))?;
+                // uint128 liquidityGross is packed before int128 liquidityNet.
+                let liquidity_net = (info >> 128_usize).to::<u128>() as i128;
+                ticks.insert(tick, liquidity_net);
+            }
+        }
+
+        Ok(Self {
+            address,
+            fee,
+            tick_spacing,
+            sqrt_price_x96,
+            tick,
+            liquidity,
+            tick_bitmap,
+            ticks,
+        })
+    }
+
+    /// Returns the amount of the other token received for `amount_in`.
+    pub fn exact_input(&self, zero_for_one: bool, amount_in: U256) -> anyhow::Result<U256> {
+        let result = self.swap(zero_for_one, I256::try_from(amount_in)?, None)?;
+        let amount_out = if zero_for_one {
+            result.amount1
+        } else {
+            result.amount0
+        };
+        Ok(amount_out.unsigned_abs())
+    }
+
+    /// Returns the amount of the other token required to receive `amount_out`.
+    pub fn exact_output(&self, zero_for_one: bool, amount_out: U256) -> anyhow::Result<U256> {
+        let result = self.swap(zero_for_one, -I256::try_from(amount_out)?, None)?;
+        let amount_in = if zero_for_one {
+            result.amount0
+        } else {
+            result.amount1
+        };
+        Ok(amount_in.into_raw())
+    }
+
+    /// Simulates a swap without changing the pool state.
+    ///
+    /// `amount_specified` is positive for exact input and negative for exact output,
+    /// `sqrt_price_limit_x96` defaults to the MIN/MAX price (depending on direction).
+    pub fn swap(
+        &self,
+        zero_for_one: bool,
+        amount_specified: I256,
+        sqrt_price_limit_x96: Option<U256>,
+    ) -> anyhow::Result<SwapResult> {
+        ensure!(!amount_specified.is_zero(), "zero amount");
+
+        let sqrt_price_limit_x96 = sqrt_price_limit_x96.unwrap_or(if zero_for_one {
+            MIN_SQRT_RATIO + U256::from(1)
+        } else {
+            MAX_SQRT_RATIO - U256::from(1)
+        });
+        if zero_for_one {
+            ensure!(
+                sqrt_price_limit_x96 < self.sqrt_price_x96 && sqrt_price_limit_x96 > MIN_SQRT_RATIO,
+                "SPL"
+            );
+        } else {
+            ensure!(
+                sqrt_price_limit_x96 > self.sqrt_price_x96 && sqrt_price_limit_x96 < MAX_SQRT_RATIO,
+                "SPL"
+            );
+        }
+
+        let exact_input = amount_specified.is_positive();
+
+        let mut amount_specified_remaining = amount_specified;
+        let mut amount_calculated = I256::ZERO;
+        let mut sqrt_price_x96 = self.sqrt_price_x96;
+        let mut tick = self.tick;
+        let mut liquidity = self.liquidity;
+        let mut ticks_crossed = 0;
+
+        while !amount_specified_remaining.is_zero() && sqrt_price_x96 != sqrt_price_limit_x96 {
+            let sqrt_price_start_x96 = sqrt_price_x96;
+
+            let (tick_next, initialized) = next_initialized_tick_within_one_word(
+                &self.tick_bitmap,
+                tick,
+                self.tick_spacing,
+                zero_for_one,
+            )?;
+            let tick_next = tick_next.clamp(MIN_TICK, MAX_TICK);
+            let sqrt_price_next_x96 = get_sqrt_ratio_at_tick(tick_next)?;
+
+            let sqrt_price_target_x96 = if (zero_for_one
+                && sqrt_price_next_x96 < sqrt_price_limit_x96)
+                || (!zero_for_one && sqrt_price_next_x96 > sqrt_price_limit_x96)
+            {
+                sqrt_price_limit_x96
+            } else {
+                sqrt_price_next_x96
+            };
+
+            let step = compute_swap_step(
+                sqrt_price_x96,
+                sqrt_price_target_x96,
+                liquidity,
+                amount_specified_remaining,
+                self.fee,
+            )?;
+            sqrt_price_x96 = step.sqrt_ratio_next_x96;
+
+            let amount_in = I256::try_from(step.amount_in + step.fee_amount)?;
+            let amount_out = I256::try_from(step.amount_out)?;
+            if exact_input {
+                amount_specified_remaining -= amount_in;
+                amount_calculated -= amount_out;
+            } else {
+                amount_specified_remaining += amount_out;
+                amount_calculated += amount_in;
+            }
+
+            if sqrt_price_x96 == sqrt_price_next_x96 {
+                if initialized {
+                    let liquidity_net = *self
+                        .ticks
+                        .get(&tick_next)
+                        .ok_or_else(|| anyhow!("tick {tick_next} is not loaded"))?;
+                    let liquidity_net = if zero_for_one {
+                        -liquidity_net
+                    } else {
+                        liquidity_net
+                    };
+                    liquidity = add_delta(liquidity, liquidity_net)?;
+                    ticks_crossed += 1;
+                }
+                tick = if zero_for_one {
+                    tick_next - 1
+                } else {
+                    tick_next
+                };
+            } else if sqrt_price_x96 != sqrt_price_start_x96 {
+                tick = get_tick_at_sqrt_ratio(sqrt_price_x96)?;
+            }
+        }
+
+        let (amount0, amount1) = if zero_for_one == exact_input {
+            (
+                amount_specified - amount_specified_remaining,
+                amount_calculated,
+            )
+        } else {
+            (
+                amount_calculated,
+                amount_specified - amount_specified_remaining,
+            )
+        };
+
+        Ok(SwapResult {
+            amount0,
+            amount1,
+            sqrt_price_x96_after: sqrt_price_x96,
+            tick_after: tick,
+            liquidity_after: liquidity,
+            ticks_crossed,
+        })
+    }
+}
+
+fn mapping_slot(key: I256, slot: u64) -> U256 {
+    keccak256((key, U256::from(slot)).abi_encode()).into()
+}
+
+fn add_delta(x: u128, y: i128) -> anyhow::Result<u128> {
+    let z = if y < 0 {
+        x.checked_sub(y.unsigned_abs())
+    } else {
+        x.checked_add(y as u128)
+    };
+    z.ok_or_else(|| anyhow!("liquidity delta out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::uint;
+    use revm::database::{CacheDB, EmptyDB};
+
+    use super::*;
+    use crate::uni_v3::{
+        full_math::mul_div_rounding_up,
+        sqrt_price_math::{get_amount0_delta, get_amount1_delta},
+        tick_math::get_tick_at_sqrt_ratio,
+    };
+
+    const Q96: U256 = uint!(0x1000000000000000000000000_U256);
+
+    #[test]
+    fn loads_packed_slot0() {
+        let sqrt_price_x96 = uint!(78353418259736015728675615163_U256);
+        // tick -223 as int24, then observation indexes and fee protocol that are ignored.
+        let slot0 = sqrt_price_x96 | (U256::from(0xff_ff21) << 160) | (U256::from(7) << 184);
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_storage(Address::ZERO, U256::from(SLOT0_SLOT), slot0)
+            .unwrap();
+
+        assert_eq!(
+            load_slot0(&mut db, Address::ZERO).unwrap(),
+            (sqrt_price_x96, -223)
+        );
+    }
+
+    #[test]
+    fn rejects_liquidity_wider_than_u128() {
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_storage(Address::ZERO, U256::from(SLOT0_SLOT), Q96)
+            .unwrap();
+        db.insert_account_storage(Address::ZERO, U256::from(LIQUIDITY_SLOT), U256::MAX)
+            .unwrap();
+        assert!(UniV3Pool::load(&mut db, Address::ZERO, 3000, 0).is_err());
+
+        db.insert_account_storage(Address::ZERO, U256::from(LIQUIDITY_SLOT), U256::from(7))
+            .unwrap();
+        let pool = UniV3Pool::load(&mut db, Address::ZERO, 3000, 0).unwrap();
+        assert_eq!(pool.liquidity, 7);
+    }
+
+    #[test]
+    fn slippage_limit_moves_price_by_bps() {
+        // 1% of price is ~100 ticks either way.
+        let limit = slippage_sqrt_price_limit(Q96, true, 100);
+        assert_eq!(get_tick_at_sqrt_ratio(limit).unwrap(), -101);
+        let limit = slippage_sqrt_price_limit(Q96, false, 100);
+        assert_eq!(get_tick_at_sqrt_ratio(limit).unwrap(), 99);
+
+        let limit = slippage_sqrt_price_limit(Q96, true, 10_000);
+        assert_eq!(limit, MIN_SQRT_RATIO + U256::from(1));
+    }
+
+    const ONE: i128 = 1_000_000_000_000_000_000;
+
+    /// 0.3% pool at price 1 with a single position of `ONE / 4` liquidity in
+    /// `[lower, upper]`, as in the v3-core "swapping across gaps" tests.
+    fn gap_pool(lower: i32, upper: i32) -> UniV3Pool {
+        let liquidity = ONE / 4;
+        let ticks = HashMap::from([(lower, liquidity), (upper, -liquidity)]);
+        let mut tick_bitmap: HashMap<_, _> = (-8..=8).map(|word| (word, U256::ZERO)).collect();
+        for tick in ticks.keys() {
+            let (word_pos, bit_pos) = position(tick / 60);
+            *tick_bitmap.get_mut(&word_pos).unwrap() |= U256::from(1) << bit_pos;
+        }
+        UniV3Pool {
+            address: Address::ZERO,
+            fee: 3000,
+            tick_spacing: 60,
+            sqrt_price_x96: Q96,
+            tick: 0,
+            liquidity: 0,
+            tick_bitmap,
+            ticks,
+        }
+    }
+
+    // Expected values come from v3-core `test/UniswapV3Pool.spec.ts`
+    // ("swapping across gaps works in 1 for 0 / 0 for 1 direction"): after swapping
+    // 1e18 in, burning the position returns 30027458295511 of the output token and
+    // 996999999999999999 of the input one, with the tick at 120196 / -120197.
+
+    #[test]
+    fn exact_input_crosses_gap_up() {
+        let result = gap_pool(120000, 121200)
+            .swap(false, I256::try_from(ONE).unwrap(), None)
+            .unwrap();
+
+        assert_eq!(result.amount1, I256::try_from(ONE).unwrap());
+        assert_eq!(result.tick_after, 120196);
+        assert_eq!(result.liquidity_after, ONE as u128 / 4);
+        assert_eq!(result.ticks_crossed, 1);
+
+        let lower = get_sqrt_ratio_at_tick(120000).unwrap();
+        let upper = get_sqrt_ratio_at_tick(121200).unwrap();
+        let price = result.sqrt_price_x96_after;
+        let liquidity = ONE as u128 / 4;
+        assert_eq!(
+            get_amount0_delta(price, upper, liquidity, false).unwrap(),
+            U256::from(30027458295511_u64)
+        );
+        assert_eq!(
+            get_amount1_delta(lower, price, liquidity, false).unwrap(),
+            U256::from(996999999999999999_u64)
+        );
+        // The swap output is what the position held minus what is left in it.
+        let minted = get_amount0_delta(lower, upper, liquidity, true).unwrap();
+        assert_eq!(
+            U256::from(result.amount0.unsigned_abs()),
+            minted - U256::from(30027458295511_u64) - U256::from(1)
+        );
+    }
+
+    #[test]
+    fn exact_input_crosses_gap_down() {
+        let result = gap_pool(-121200, -120000)
+            .swap(true, I256::try_from(ONE).unwrap(), None)
+            .unwrap();
+
+        assert_eq!(result.amount0, I256::try_from(ONE).unwrap());
+        assert_eq!(result.tick_after, -120197);
+        assert_eq!(result.ticks_crossed, 1);
+
+        let lower = get_sqrt_ratio_at_tick(-121200).unwrap();
+        let upper = get_sqrt_ratio_at_tick(-120000).unwrap();
+        let price = result.sqrt_price_x96_after;
+        let liquidity = ONE as u128 / 4;
+        assert_eq!(
+            get_amount0_delta(price, upper, liquidity, false).unwrap(),
+            U256::from(996999999999999999_u64)
+        );
+        assert_eq!(
+            get_amount1_delta(lower, price, liquidity, false).unwrap(),
+            U256::from(30027458295511_u64)
+        );
+    }
+
+    #[test]
+    fn exact_output_matches_exact_input() {
+        let pool = gap_pool(120000, 121200);
+        let exact_input = pool
+            .swap(false, I256::try_from(ONE).unwrap(), None)
+            .unwrap();
+        let exact_output = pool.swap(false, exact_input.amount0, None).unwrap();
+
+        assert_eq!(exact_output.amount0, exact_input.amount0);
+        assert!(exact_output.amount1 <= exact_input.amount1);
+        assert_eq!(exact_output.tick_after, 120196);
+    }
+
+    #[test]
+    fn stops_at_the_price_limit() {
+        let limit = get_sqrt_ratio_at_tick(120120).unwrap();
+        let result = gap_pool(120000, 121200)
+            .swap(false, I256::try_from(ONE).unwrap(), Some(limit))
+            .unwrap();
+
+        assert_eq!(result.sqrt_price_x96_after, limit);
+        assert_eq!(result.tick_after, 120120);
+        // Nothing is paid to cross the gap, then the input of one step plus its fee.
+        let lower = get_sqrt_ratio_at_tick(120000).unwrap();
+        let amount_in = get_amount1_delta(lower, limit, ONE as u128 / 4, true).unwrap();
+        let fee = mul_div_rounding_up(amount_in, U256::from(3000), U256::from(997_000)).unwrap();
+        assert_eq!(result.amount1, I256::try_from(amount_in + fee).unwrap());
+    }
+}
@@ -0,0 +1,223 @@
+use alloy::primitives::U256;
+use anyhow::{anyhow, ensure};
+
+use super::full_math::{div_rounding_up, mul_div, mul_div_rounding_up};
+
+const RESOLUTION: usize = 96;
+const Q96: U256 = U256::from_limbs([0, 1 << 32, 0, 0]);
+
+fn max_u160() -> U256 {
+    (U256::from(1) << 160) - U256::from(1)
+}
+
+fn to_u160(value: U256) -> anyhow::Result<U256> {
+    ensure!(value <= max_u160(), "sqrt price overflows uint160");
+    Ok(value)
+}
+
+/// Gets the next sqrt price given a delta of token0, always rounds up.
+pub fn get_next_sqrt_price_from_amount0_rounding_up(
+    sqrt_px96: U256,
+    liquidity: u128,
+    amount: U256,
+    add: bool,
+) -> anyhow::Result<U256> {
+    if amount.is_zero() {
+        return Ok(sqrt_px96);
+    }
+    let numerator1 = U256::from(liquidity) << RESOLUTION;
+
+    if add {
+        if let Some(product) = amount.checked_mul(sqrt_px96)
+            && let Some(denominator) = numerator1.checked_add(product)
+        {
+            return mul_div_rounding_up(numerator1, sqrt_px96, denominator);
+        }
+        let denominator = (numerator1 / sqrt_px96)
+            .checked_add(amount)
+            .ok_or_else(|| anyhow!("amount0 overflow"))?;
+        Ok(div_rounding_up(numerator1, denominator))
+    } else {
+        let product = amount
+            .checked_mul(sqrt_px96)
+            .ok_or_else(|| anyhow!("amount0 overflow"))?;
+        ensure!(numerator1 > product, "not enough liquidity for amount0");
+        to_u160(mul_div_rounding_up(
+            numerator1,
+            sqrt_px96,
+            numerator1 - product,
+        )?)
+    }
+}
+
+/// Gets the next sqrt price given a delta of token1, always rounds down.
+pub fn get_next_sqrt_price_from_amount1_rounding_down(
+    sqrt_px96: U256,
+    liquidity: u128,
+    amount: U256,
+    add: bool,
+) -> anyhow::Result<U256> {
+    let liquidity = U256::from(liquidity);
+
+    if add {
+        let quotient = if amount <= max_u160() {
+            (amount << RESOLUTION) / liquidity
+        } else {
+            mul_div(amount, Q96, liquidity)?
+        };
+        to_u160(
+            sqrt_px96
+                .checked_add(quotient)
+                .ok_or_else(|| anyhow!("amount1 overflow"))?,
+        )
+    } else {
+        let quotient = if amount <= max_u160() {
+            div_rounding_up(amount << RESOLUTION, liquidity)
+        } else {
+            mul_div_rounding_up(amount, Q96, liquidity)?
+        };
+        ensure!(sqrt_px96 > quotient, "not enough liquidity for amount1");
+        Ok(sqrt_px96 - quotient)
+    }
+}
+
+/// Gets the next sqrt price given an input amount of token0 or token1.
+pub fn get_next_sqrt_price_from_input(
+    sqrt_px96: U256,
+    liquidity: u128,
+    amount_in: U256,
+    zero_for_one: bool,
+) -> anyhow::Result<U256> {
+    ensure!(!sqrt_px96.is_zero() && liquidity > 0, "empty pool");
+    if zero_for_one {
+        get_next_sqrt_price_from_amount0_rounding_up(sqrt_px96, liquidity, amount_in, true)
+    } else {
+        get_next_sqrt_price_from_amount1_rounding_down(sqrt_px96, liquidity, amount_in, true)
+    }
+}
+
+/// Gets the next sqrt price given an output amount of token0 or token1.
+pub fn get_next_sqrt_price_from_output(
+    sqrt_px96: U256,
+    liquidity: u128,
+    amount_out: U256,
+    zero_for_one: bool,
+) -> anyhow::Result<U256> {
+    ensure!(!sqrt_px96.is_zero() && liquidity > 0, "empty pool");
+    if zero_for_one {
+        get_next_sqrt_price_from_amount1_rounding_down(sqrt_px96, liquidity, amount_out, false)
+    } else {
+        get_next_sqrt_price_from_amount0_rounding_up(sqrt_px96, liquidity, amount_out, false)
+    }
+}
+
+/// Gets the amount0 delta between two prices.
+pub fn get_amount0_delta(
+    sqrt_ratio_a_x96: U256,
+    sqrt_ratio_b_x96: U256,
+    liquidity: u128,
+    round_up: bool,
+) -> anyhow::Result<U256> {
+    let (a, b) = if sqrt_ratio_a_x96 > sqrt_ratio_b_x96 {
+        (sqrt_ratio_b_x96, sqrt_ratio_a_x96)
+    } else {
+        (sqrt_ratio_a_x96, sqrt_ratio_b_x96)
+    };
+    ensure!(!a.is_zero(), "zero sqrt price");
+
+    let numerator1 = U256::from(liquidity) << RESOLUTION;
+    let numerator2 = b - a;
+
+    if round_up {
+        Ok(div_rounding_up(
+            mul_div_rounding_up(numerator1, numerator2, b)?,
+            a,
+        ))
+    } else {
+        Ok(mul_div(numerator1, numerator2, b)? / a)
+    }
+}
+
+/// Gets the amount1 delta between two prices.
+pub fn get_amount1_delta(
+    sqrt_ratio_a_x96: U256,
+    sqrt_ratio_b_x96: U256,
+    liquidity: u128,
+    round_up: bool,
+) -> anyhow::Result<U256> {
+    let (a, b) = if sqrt_ratio_a_x96 > sqrt_ratio_b_x96 {
+        (sqrt_ratio_b_x96, sqrt_ratio_a_x96)
+    } else {
+        (sqrt_ratio_a_x96, sqrt_ratio_b_x96)
+    };
+
+    if round_up {
+        mul_div_rounding_up(U256::from(liquidity), b - a, Q96)
+    } else {
+        mul_div(U256::from(liquidity), b - a, Q96)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::uint;
+
+    use super::*;
+
+    const ONE: u128 = 1_000_000_000_000_000_000;
+    /// `encodePriceSqrt(121, 100)`, a price of 1.21.
+    const SQRT_PRICE_1_21: U256 = uint!(87150978765690771352898345369_U256);
+
+    #[test]
+    fn amount0_delta_rounds_up_by_one() {
+        let up = get_amount0_delta(Q96, SQRT_PRICE_1_21, ONE, true).unwrap();
+        let down = get_amount0_delta(Q96, SQRT_PRICE_1_21, ONE, false).unwrap();
+        assert_eq!(up, U256::from(90909090909090910_u64));
+        assert_eq!(down, up - U256::from(1));
+    }
+
+    #[test]
+    fn amount1_delta_rounds_up_by_one() {
+        let up = get_amount1_delta(Q96, SQRT_PRICE_1_21, ONE, true).unwrap();
+        let down = get_amount1_delta(Q96, SQRT_PRICE_1_21, ONE, false).unwrap();
+        assert_eq!(up, U256::from(100000000000000000_u64));
+        assert_eq!(down, up - U256::from(1));
+    }
+
+    #[test]
+    fn amount_deltas_ignore_price_order() {
+        assert_eq!(
+            get_amount0_delta(SQRT_PRICE_1_21, Q96, ONE, true).unwrap(),
+            get_amount0_delta(Q96, SQRT_PRICE_1_21, ONE, true).unwrap()
+        );
+        assert_eq!(
+            get_amount1_delta(SQRT_PRICE_1_21, Q96, ONE, false).unwrap(),
+            get_amount1_delta(Q96, SQRT_PRICE_1_21, ONE, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn next_sqrt_price_from_input() {
+        // 0.1 token1 into 1e18 liquidity at a price of 1.
+        assert_eq!(
+            get_next_sqrt_price_from_input(Q96, ONE, U256::from(ONE / 10), false).unwrap(),
+            uint!(87150978765690771352898345369_U256)
+        );
+        // 0.1 token0, the price rounds up.
+        assert_eq!(
+            get_next_sqrt_price_from_input(Q96, ONE, U256::from(ONE / 10), true).unwrap(),
+            uint!(72025602285694852357767227579_U256)
+        );
+        assert_eq!(
+            get_next_sqrt_price_from_input(Q96, ONE, U256::ZERO, true).unwrap(),
+            Q96
+        );
+    }
+
+    #[test]
+    fn next_sqrt_price_from_output_fails_past_the_reserves() {
+        // Exactly the virtual reserve of token1.
+        assert!(get_next_sqrt_price_from_output(Q96, ONE, U256::from(ONE), true).is_err());
+        assert!(get_next_sqrt_price_from_output(Q96, ONE, U256::from(ONE), false).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use alloy::primitives::{I256, U256};
+use anyhow::ensure;
+
+use super::{
+    full_math::{mul_div, mul_div_rounding_up},
+    sqrt_price_math::{
+        get_amount0_delta, get_amount1_delta, get_next_sqrt_price_from_input,
+        get_next_sqrt_price_from_output,
+    },
+};
+
+const FEE_DENOMINATOR: u32 = 1_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_ratio_next_x96: U256,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub fee_amount: U256,
+}
+
+/// Computes the result of swapping some amount in, or amount out, given the parameters of the swap.
+///
+/// `amount_remaining` is positive for exact input and negative for exact output,
+/// `fee_pips` is in hundredths of a bip (3000 = 0.3%) and must be below 100%.
+pub fn compute_swap_step(
+    sqrt_ratio_current_x96: U256,
+    sqrt_ratio_target_x96: U256,
+    liquidity: u128,
+    amount_remaining: I256,
+    fee_pips: u32,
+) -> anyhow::Result<SwapStep> {
+    ensure!(
+        fee_pips < FEE_DENOMINATOR,
+        "fee {fee_pips} is not below 100%"
+    );
+
+    let zero_for_one = sqrt_ratio_current_x96 >= sqrt_ratio_target_x96;
+    let exact_in = !amount_remaining.is_negative();
+    let fee_pips_u256 = U256::from(fee_pips);
+    let fee_complement = U256::from(FEE_DENOMINATOR - fee_pips);
+
+    let mut amount_in = U256::ZERO;
+    let mut amount_out = U256::ZERO;
+
+    let sqrt_ratio_next_x96 = if exact_in {
+        let amount_remaining_less_fee = mul_div(
+            amount_remaining.into_raw(),
+            fee_complement,
+            U256::from(FEE_DENOMINATOR),
+        )?;
+        amount_in = if zero_for_one {
+            get_amount0_delta(
+                sqrt_ratio_target_x96,
+                sqrt_ratio_current_x96,
+                liquidity,
+                true,
+            )?
+        } else {
+            get_amount1_delta(
+                sqrt_ratio_current_x96,
+                sqrt_ratio_target_x96,
+                liquidity,
+                true,
+            )?
+        };
+        if amount_remaining_less_fee >= amount_in {
+            sqrt_ratio_target_x96
+        } else {
+            get_next_sqrt_price_from_input(
+                sqrt_ratio_current_x96,
+                liquidity,
+                amount_remaining_less_fee,
+                zero_for_one,
+            )?
+        }
+    } else {
+        amount_out = if zero_for_one {
+            get_amount1_delta(
+                sqrt_ratio_target_x96,
+                sqrt_ratio_current_x96,
+                liquidity,
+                false,
+            )?
+        } else {
+            get_amount0_delta(
+                sqrt_ratio_current_x96,
+                sqrt_ratio_target_x96,
+                liquidity,
+                false,
+            )?
+        };
+        if amount_remaining.unsigned_abs() >= amount_out {
+            sqrt_ratio_target_x96
+        } else {
+            get_next_sqrt_price_from_output(
+                sqrt_ratio_current_x96,
+                liquidity,
+                amount_remaining.unsigned_abs(),
+                zero_for_one,
+            )?
+        }
+    };
+
+    let max = sqrt_ratio_target_x96 == sqrt_ratio_next_x96;
+
+    if zero_for_one {
+        if !(max && exact_in) {
+            amount_in =
+                get_amount0_delta(sqrt_ratio_next_x96, sqrt_ratio_current_x96, liquidity, true)?;
+        }
+        if !max || exact_in {
+            amount_out = get_amount1_delta(
+                sqrt_ratio_next_x96,
+                sqrt_ratio_current_x96,
+                liquidity,
+                false,
+            )?;
+        }
+    } else {
+        if !(max && exact_in) {
+            amount_in =
+                get_amount1_delta(sqrt_ratio_current_x96, sqrt_ratio_next_x96, liquidity, true)?;
+        }
+        if !max || exact_in {
+            amount_out = get_amount0_delta(
+                sqrt_ratio_current_x96,
+                sqrt_ratio_next_x96,
+                liquidity,
+                false,
+            )?;
+        }
+    }
+
+    // Cap the output amount to not exceed the remaining output amount.
+    if !exact_in && amount_out > amount_remaining.unsigned_abs() {
+        amount_out = amount_remaining.unsigned_abs();
+    }
+
+    let fee_amount = if exact_in && sqrt_ratio_next_x96 != sqrt_ratio_target_x96 {
+        // We didn't reach the target, so take the remainder of the maximum input as fee.
+        amount_remaining.into_raw() - amount_in
+    } else {
+        mul_div_rounding_up(amount_in, fee_pips_u256, fee_complement)?
+    };
+
+    Ok(SwapStep {
+        sqrt_ratio_next_x96,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::uint;
+
+    use super::*;
+
+    const ONE: u128 = 1_000_000_000_000_000_000;
+    const Q96: U256 = U256::from_limbs([0, 1 << 32, 0, 0]);
+    /// `encodePriceSqrt(101, 100)`
+    const SQRT_PRICE_1_01: U256 = uint!(79623317895830914510639640423_U256);
+    /// `encodePriceSqrt(1000, 100)`
+    const SQRT_PRICE_10: U256 = uint!(250541448375047931186413801569_U256);
+    /// `encodePriceSqrt(10000, 100)`
+    const SQRT_PRICE_100: U256 = uint!(792281625142643375935439503360_U256);
+
+    fn signed(amount: u128) -> I256 {
+        I256::try_from(amount).unwrap()
+    }
+
+    #[test]
+    fn exact_in_capped_at_target() {
+        let step = compute_swap_step(Q96, SQRT_PRICE_1_01, 2 * ONE, signed(ONE), 600).unwrap();
+        assert_eq!(
+            step,
+            SwapStep {
+                sqrt_ratio_next_x96: SQRT_PRICE_1_01,
+                amount_in: U256::from(9975124224178055_u64),
+                amount_out: U256::from(9925619580021728_u64),
+                fee_amount: U256::from(5988667735148_u64),
+            }
+        );
+    }
+
+    #[test]
+    fn exact_out_capped_at_target() {
+        let step = compute_swap_step(Q96, SQRT_PRICE_1_01, 2 * ONE, -signed(ONE), 600).unwrap();
+        assert_eq!(
+            step,
+            SwapStep {
+                sqrt_ratio_next_x96: SQRT_PRICE_1_01,
+                amount_in: U256::from(9975124224178055_u64),
+                amount_out: U256::from(9925619580021728_u64),
+                fee_amount: U256::from(5988667735148_u64),
+            }
+        );
+    }
+
+    #[test]
+    fn exact_in_fully_spent() {
+        let step = compute_swap_step(Q96, SQRT_PRICE_10, 2 * ONE, signed(ONE), 600).unwrap();
+        assert_eq!(step.amount_in, U256::from(999400000000000000_u64));
+        assert_eq!(step.fee_amount, U256::from(600000000000000_u64));
+        assert_eq!(step.amount_out, U256::from(666399946655997866_u64));
+        assert_eq!(step.amount_in + step.fee_amount, U256::from(ONE));
+        assert!(step.sqrt_ratio_next_x96 < SQRT_PRICE_10);
+    }
+
+    #[test]
+    fn exact_out_fully_received() {
+        let step = compute_swap_step(Q96, SQRT_PRICE_100, 2 * ONE, -signed(ONE), 600).unwrap();
+        assert_eq!(step.amount_in, U256::from(2 * ONE));
+        assert_eq!(step.fee_amount, U256::from(1200720432259356_u64));
+        assert_eq!(step.amount_out, U256::from(ONE));
+        assert!(step.sqrt_ratio_next_x96 < SQRT_PRICE_100);
+    }
+
+    #[test]
+    fn exact_out_capped_at_desired_amount() {
+        let step = compute_swap_step(
+            uint!(417332158212080721273783715441582_U256),
+            uint!(1452870262520218020823638996_U256),
+            159344665391607089467575320103,
+            -signed(1),
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            step,
+            SwapStep {
+                sqrt_ratio_next_x96: uint!(417332158212080721273783715441581_U256),
+                amount_in: U256::from(1),
+                amount_out: U256::from(1),
+                fee_amount: U256::from(1),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_fee_of_100_percent_or_more() {
+        for fee_pips in [FEE_DENOMINATOR, FEE_DENOMINATOR + 1, u32::MAX] {
+            assert!(
+                compute_swap_step(Q96, SQRT_PRICE_1_01, 2 * ONE, signed(ONE), fee_pips).is_err()
+            );
+        }
+    }
+}
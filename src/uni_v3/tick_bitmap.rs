@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use alloy::primitives::U256;
+use anyhow::anyhow;
+
+/// Returns the word and the bit position of a compressed tick in the bitmap.
+pub fn position(compressed: i32) -> (i16, u8) {
+    ((compressed >> 8) as i16, (compressed & 0xff) as u8)
+}
+
+/// Returns the next initialized tick contained in the same word (or adjacent word)
+/// as the tick that is either to the left (less than or equal to) or right (greater than)
+/// of the given tick, and whether it is initialized.
+///
+/// Fails if the bitmap word wasn't loaded.
+pub fn next_initialized_tick_within_one_word(
+    bitmap: &HashMap<i16, U256>,
+    tick: i32,
+    tick_spacing: i32,
+    lte: bool,
+) -> anyhow::Result<(i32, bool)> {
+    let mut compressed = tick / tick_spacing;
+    if tick < 0 && tick % tick_spacing != 0 {
+        // Round towards negative infinity.
+        compressed -= 1;
+    }
+
+    let word = |word_pos: i16| {
+        bitmap
+            .get(&word_pos)
+            .copied()
+            .ok_or_else(|| anyhow!("tick bitmap word {word_pos} is not loaded"))
+    };
+
+    if lte {
+        let (word_pos, bit_pos) = position(compressed);
+        // All the 1s at or to the right of the current bit position.
+        let mask = (U256::from(1) << bit_pos) - U256::from(1) + (U256::from(1) << bit_pos);
+        let masked = word(word_pos)? & mask;
+
+        let initialized = !masked.is_zero();
+        let next = if initialized {
+            let msb = 255 - masked.leading_zeros() as i32;
+            (compressed - (bit_pos as i32 - msb)) * tick_spacing
+        } else {
+            (compressed - bit_pos as i32) * tick_spacing
+        };
+        Ok((next, initialized))
+    } else {
+        let (word_pos, bit_pos) = position(compressed + 1);
+        // All the 1s at or to the left of the bit position.
+        let mask = !((U256::from(1) << bit_pos) - U256::from(1));
+        let masked = word(word_pos)? & mask;
+
+        let initialized = !masked.is_zero();
+        let next = if initialized {
+            let lsb = masked.trailing_zeros() as i32;
+            (compressed + 1 + (lsb - bit_pos as i32)) * tick_spacing
+        } else {
+            (compressed + 1 + (u8::MAX - bit_pos) as i32) * tick_spacing
+        };
+        Ok((next, initialized))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bitmap with a tick spacing of 1, the ticks of the Uniswap V3 `TickBitmap` tests.
+    fn bitmap() -> HashMap<i16, U256> {
+        let mut bitmap = HashMap::new();
+        for word_pos in -4..=4 {
+            bitmap.insert(word_pos, U256::ZERO);
+        }
+        for tick in [-200, -55, -4, 70, 78, 84, 139, 240, 535] {
+            let (word_pos, bit_pos) = position(tick);
+            let word = bitmap.get_mut(&word_pos).unwrap();
+            *word |= U256::from(1) << bit_pos;
+        }
+        bitmap
+    }
+
+    fn next(tick: i32, lte: bool) -> (i32, bool) {
+        next_initialized_tick_within_one_word(&bitmap(), tick, 1, lte).unwrap()
+    }
+
+    #[test]
+    fn next_to_the_right() {
+        assert_eq!(next(78, false), (84, true));
+        assert_eq!(next(-55, false), (-4, true));
+        assert_eq!(next(77, false), (78, true));
+        assert_eq!(next(-56, false), (-55, true));
+        // Not past the end of the word.
+        assert_eq!(next(255, false), (511, false));
+        assert_eq!(next(383, false), (511, false));
+        assert_eq!(next(510, false), (511, false));
+        // The next word, from its last tick's neighbour.
+        assert_eq!(next(511, false), (535, true));
+        assert_eq!(next(-257, false), (-200, true));
+    }
+
+    #[test]
+    fn next_to_the_left() {
+        assert_eq!(next(78, true), (78, true));
+        assert_eq!(next(79, true), (78, true));
+        assert_eq!(next(72, true), (70, true));
+        assert_eq!(next(-1, true), (-4, true));
+        // Not past the start of the word.
+        assert_eq!(next(258, true), (256, false));
+        assert_eq!(next(256, true), (256, false));
+        assert_eq!(next(900, true), (768, false));
+        assert_eq!(next(1023, true), (768, false));
+        assert_eq!(next(-201, true), (-256, false));
+        assert_eq!(next(-257, true), (-512, false));
+    }
+
+    #[test]
+    fn negative_ticks_round_down_to_the_spacing() {
+        let mut bitmap = HashMap::from([(-1, U256::ZERO), (0, U256::ZERO)]);
+        // Tick -120 with a spacing of 60 is compressed to -2.
+        let (word_pos, bit_pos) = position(-2);
+        bitmap.insert(word_pos, U256::from(1) << bit_pos);
+
+        for tick in [-1, -60, -61] {
+            assert_eq!(
+                next_initialized_tick_within_one_word(&bitmap, tick, 60, true).unwrap(),
+                (-120, true)
+            );
+        }
+        assert_eq!(
+            next_initialized_tick_within_one_word(&bitmap, -121, 60, true).unwrap(),
+            (-15360, false)
+        );
+    }
+
+    #[test]
+    fn fails_on_missing_words() {
+        assert!(next_initialized_tick_within_one_word(&bitmap(), 2000, 1, true).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use alloy::primitives::{I256, U256, uint};
+use anyhow::ensure;
+
+pub const MIN_TICK: i32 = -887272;
+pub const MAX_TICK: i32 = -MIN_TICK;
+
+/// `get_sqrt_ratio_at_tick(MIN_TICK)`
+pub const MIN_SQRT_RATIO: U256 = uint!(4295128739_U256);
+/// `get_sqrt_ratio_at_tick(MAX_TICK)`
+pub const MAX_SQRT_RATIO: U256 = uint!(1461446703485210103287273052203988822378723970342_U256);
+
+/// `sqrt(1.0001^-2^i) * 2^128` for every bit `i` of the absolute tick, starting from 0x2.
+const RATIOS: [u128; 19] = [
+    0xfff97272373d413259a46990580e213a,
+    0xfff2e50f5f656932ef12357cf3c7fdcc,
+    0xffe5caca7e10e4e61c3624eaa0941cd0,
+    0xffcb9843d60f6159c9db58835c926644,
+    0xff973b41fa98c081472e6896dfb254c0,
+    0xff2ea16466c96a3843ec78b326b52861,
+    0xfe5dee046a99a2a811c461f1969c3053,
+    0xfcbe86c7900a88aedcffc83b479aa3a4,
+    0xf987a7253ac413176f2b074cf7815e54,
+    0xf3392b0822b70005940c7a398e4b70f3,
+    0xe7159475a2c29b7443b29c7fa6e889d9,
+    0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e5,
+    0x70d869a156d2a1b890bb3df62baf32f7,
+    0x31be135f97d08fd981231505542fcfa6,
+    0x9aa508b5b7a84e1c677de54f3e99bc9,
+    0x5d6af8dedb81196699c329225ee604,
+    0x2216e584f5fa1ea926041bedfe98,
+    0x48a170391f7dc42444e8fa2,
+];
+
+/// Calculates `sqrt(1.0001^tick) * 2^96`.
+pub fn get_sqrt_ratio_at_tick(tick: i32) -> anyhow::Result<U256> {
+    let abs_tick = tick.unsigned_abs();
+    ensure!(abs_tick <= MAX_TICK as u32, "tick {tick} out of range");
+
+    let mut ratio = if abs_tick & 0x1 != 0 {
+        U256::from(0xfffcb933bd6fad37aa2d162d1a594001_u128)
+    } else {
+        U256::from(1) << 128
+    };
+    for (i, r) in RATIOS.iter().enumerate() {
+        if abs_tick & (0x2 << i) != 0 {
+            ratio = (ratio * U256::from(*r)) >> 128;
+        }
+    }
+
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+
+    // Rounds up to make sure that `get_tick_at_sqrt_ratio` of the result is `tick`.
+    let round_up = !(ratio & U256::from(u32::MAX)).is_zero();
+    Ok((ratio >> 32) + U256::from(round_up as u8))
+}
+
+/// Calculates the greatest tick such that `get_sqrt_ratio_at_tick(tick) <= sqrt_price_x96`.
+pub fn get_tick_at_sqrt_ratio(sqrt_price_x96: U256) -> anyhow::Result<i32> {
+    ensure!(
+        sqrt_price_x96 >= MIN_SQRT_RATIO && sqrt_price_x96 < MAX_SQRT_RATIO,
+        "sqrt price {sqrt_price_x96} out of range"
+    );
+
+    let ratio = sqrt_price_x96 << 32_usize;
+    let msb = 255 - ratio.leading_zeros();
+    let mut r = if msb >= 128 {
+        ratio >> (msb - 127)
+    } else {
+        ratio << (127 - msb)
+    };
+
+    // Integer part of log2 in Q64.64, the fractional part is computed bit by bit.
+    let mut log_2: I256 = (I256::try_from(msb).unwrap() - I256::try_from(128).unwrap()) << 64_usize;
+    for shift in (50..=63).rev() {
+        r = (r * r) >> 127_usize;
+        let f: U256 = r >> 128_usize;
+        log_2 |= I256::from_raw(f << shift);
+        r >>= f.to::<usize>();
+    }
+
+    // 128.128 number
+    let log_sqrt10001: I256 = log_2 * I256::from_raw(uint!(255738958999603826347141_U256));
+
+    let tick_low = (log_sqrt10001
+        - I256::from_raw(uint!(3402992956809132418596140100660247210_U256)))
+    .asr(128)
+    .as_i32();
+    let tick_high = (log_sqrt10001
+        + I256::from_raw(uint!(291339464771989622907027621153398088495_U256)))
+    .asr(128)
+    .as_i32();
+
+    if tick_low == tick_high || get_sqrt_ratio_at_tick(tick_high)? > sqrt_price_x96 {
+        Ok(tick_low)
+    } else {
+        Ok(tick_high)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const Q96: U256 = U256::from_limbs([0, 1 << 32, 0, 0]);
+
+    #[test]
+    fn sqrt_ratio_at_bounds_and_zero() {
+        assert_eq!(get_sqrt_ratio_at_tick(MIN_TICK).unwrap(), MIN_SQRT_RATIO);
+        assert_eq!(get_sqrt_ratio_at_tick(MAX_TICK).unwrap(), MAX_SQRT_RATIO);
+        assert_eq!(get_sqrt_ratio_at_tick(0).unwrap(), Q96);
+        assert_eq!(
+            get_sqrt_ratio_at_tick(MIN_TICK + 1).unwrap(),
+            U256::from(4295343490_u64)
+        );
+        assert_eq!(
+            get_sqrt_ratio_at_tick(MAX_TICK - 1).unwrap(),
+            uint!(1461373636630004318706518188784493106690254656249_U256)
+        );
+    }
+
+    #[test]
+    fn sqrt_ratio_rejects_out_of_range_ticks() {
+        assert!(get_sqrt_ratio_at_tick(MIN_TICK - 1).is_err());
+        assert!(get_sqrt_ratio_at_tick(MAX_TICK + 1).is_err());
+    }
+
+    #[test]
+    fn tick_at_sqrt_ratio_bounds() {
+        assert_eq!(get_tick_at_sqrt_ratio(MIN_SQRT_RATIO).unwrap(), MIN_TICK);
+        assert_eq!(
+            get_tick_at_sqrt_ratio(MAX_SQRT_RATIO - U256::from(1)).unwrap(),
+            MAX_TICK - 1
+        );
+        assert!(get_tick_at_sqrt_ratio(MIN_SQRT_RATIO - U256::from(1)).is_err());
+        assert!(get_tick_at_sqrt_ratio(MAX_SQRT_RATIO).is_err());
+    }
+
+    #[test]
+    fn tick_at_sqrt_ratio_round_trips() {
+        for tick in [
+            MIN_TICK,
+            MIN_TICK + 1,
+            -887200,
+            -50000,
+            -60,
+            -1,
+            0,
+            1,
+            60,
+            50000,
+            887200,
+            MAX_TICK - 1,
+        ] {
+            let sqrt_ratio = get_sqrt_ratio_at_tick(tick).unwrap();
+            assert_eq!(get_tick_at_sqrt_ratio(sqrt_ratio).unwrap(), tick);
+            // Any price below the tick's belongs to the previous tick.
+            if tick > MIN_TICK {
+                assert_eq!(
+                    get_tick_at_sqrt_ratio(sqrt_ratio - U256::from(1)).unwrap(),
+                    tick - 1
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,492 @@
+//! Differential checks of a quote backend against a reference one,
+//! usually revm against `eth_call` at the same block.
+
+use alloy::{
+    primitives::{Bytes, U160, U256},
+    transports::{RpcError, TransportErrorKind},
+};
+
+use crate::{
+    abi::QuoteResult,
+    error::SimError,
+    quote::{QuoteBackend, QuoteRequest},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    AmountOut {
+        expected: U256,
+        actual: U256,
+    },
+    GasEstimate {
+        expected: U256,
+        actual: U256,
+    },
+    /// Compared with the gas estimate, Quoter V2 counts them for its gas model.
+    TicksCrossed {
+        expected: u32,
+        actual: u32,
+    },
+    SqrtPriceAfter {
+        expected: U160,
+        actual: U160,
+    },
+    PartialFill {
+        expected: bool,
+        actual: bool,
+    },
+    /// Only one of the backends failed, `None` for the one that quoted.
+    Revert {
+        expected: Option<String>,
+        actual: Option<String>,
+    },
+    /// Both backends reverted, with different revert data.
+    RevertReason {
+        expected: String,
+        actual: String,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub request: QuoteRequest,
+    pub mismatches: Vec<Mismatch>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub quotes: usize,
+    /// Requests both backends failed to quote.
+    pub reverts: usize,
+    /// Largest gas estimate difference, in either direction.
+    pub max_gas_delta: U256,
+    pub divergences: Vec<Divergence>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+/// Quotes every request with both backends and collects the differences.
+///
+/// Gas estimates are compared within `gas_tolerance`, `None` skips them and the
+/// ticks crossed (e.g. for the native backend that has no gas model). Failures to
+/// reach the node or the state are returned as errors, any other failed quote
+/// counts as a revert. Reverts are compared by their revert data when both
+/// backends have it, errors of the native math have none.
+pub async fn verify<R, C>(
+    reference: &R,
+    candidate: &C,
+    requests: &[QuoteRequest],
+    gas_tolerance: Option<u64>,
+) -> anyhow::Result<VerifyReport>
+where
+    R: QuoteBackend,
+    C: QuoteBackend,
+{
+    let mut report = VerifyReport::default();
+
+    for request in requests {
+        let expected = outcome(reference.quote(*request).await)?;
+        let actual = outcome(candidate.quote(*request).await)?;
+
+        let mismatches = match (expected, actual) {
+            (Ok(expected), Ok(actual)) => {
+                report.quotes += 1;
+                let gas_delta = expected.gas_estimate.abs_diff(actual.gas_estimate);
+                report.max_gas_delta = report.max_gas_delta.max(gas_delta);
+                compare(&expected, &actual, gas_delta, gas_tolerance)
+            }
+            (Err(expected), Err(actual)) => {
+                report.reverts += 1;
+                match (&expected.data, &actual.data) {
+                    (Some(expected_data), Some(actual_data)) if expected_data != actual_data => {
+                        vec![Mismatch::RevertReason {
+                            expected: expected.message,
+                            actual: actual.message,
+                        }]
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            (expected, actual) => vec![Mismatch::Revert {
+                expected: expected.err().map(|failure| failure.message),
+                actual: actual.err().map(|failure| failure.message),
+            }],
+        };
+
+        if !mismatches.is_empty() {
+            report.divergences.push(Divergence {
+                request: *request,
+                mismatches,
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+fn compare(
+    expected: &QuoteResult,
+    actual: &QuoteResult,
+    gas_delta: U256,
+    gas_tolerance: Option<u64>,
+) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    if expected.amount_out != actual.amount_out {
+        mismatches.push(Mismatch::AmountOut {
+            expected: expected.amount_out,
+            actual: actual.amount_out,
+        });
+    }
+    if expected.sqrt_price_x96_after != actual.sqrt_price_x96_after {
+        mismatches.push(Mismatch::SqrtPriceAfter {
+            expected: expected.sqrt_price_x96_after,
+            actual: actual.sqrt_price_x96_after,
+        });
+    }
+    if expected.partial_fill != actual.partial_fill {
+        mismatches.push(Mismatch::PartialFill {
+            expected: expected.partial_fill,
+            actual: actual.partial_fill,
+        });
+    }
+    if let Some(tolerance) = gas_tolerance {
+        if gas_delta > U256::from(tolerance) {
+            mismatches.push(Mismatch::GasEstimate {
+                expected: expected.gas_estimate,
+                actual: actual.gas_estimate,
+            });
+        }
+        if expected.ticks_crossed != actual.ticks_crossed {
+            mismatches.push(Mismatch::TicksCrossed {
+                expected: expected.ticks_crossed,
+                actual: actual.ticks_crossed,
+            });
+        }
+    }
+    mismatches
+}
+
+/// Failed quote, `data` is the revert data if the call reverted.
+struct Failure {
+    message: String,
+    data: Option<Bytes>,
+}
+
+/// Splits a quote into the quote or the failure, fails on infrastructure errors.
+fn outcome(quote: anyhow::Result<QuoteResult>) -> anyhow::Result<Result<QuoteResult, Failure>> {
+    match quote {
+        Ok(quote) => Ok(Ok(quote)),
+        Err(error) if is_infrastructure_error(&error) => Err(error),
+        Err(error) => Ok(Err(Failure {
+            message: error.to_string(),
+            data: revert_data(&error),
+        })),
+    }
+}
+
+/// Revert data of a simulated call or of the error response to an `eth_call`.
+fn revert_data(error: &anyhow::Error) -> Option<Bytes> {
+    if let Some(error) = error.downcast_ref::<SimError>() {
+        return match error {
+            SimError::Revert { output, .. } | SimError::UnexpectedRevert { output, .. } => {
+                Some(output.clone())
+            }
+            _ => None,
+        };
+    }
+    error
+        .downcast_ref::<RpcError<TransportErrorKind>>()?
+        .as_error_resp()?
+        .as_revert_data()
+}
+
+/// True for errors reaching the node or the state rather than executing the call.
+fn is_infrastructure_error(error: &anyhow::Error) -> bool {
+    if let Some(error) = error.downcast_ref::<SimError>() {
+        return matches!(error, SimError::Database(_) | SimError::Evm(_));
+    }
+    if let Some(error) = error.downcast_ref::<RpcError<TransportErrorKind>>() {
+        // The node answers a reverted `eth_call` with an error response.
+        return !error.is_error_resp();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::Address,
+        sol_types::{Revert, SolError},
+    };
+
+    use super::*;
+
+    /// Quotes `amount_in` plus an offset, reverts with `revert_reason` on zero
+    /// and on `reverts_at`, partially fills from `partial_fill_from`.
+    struct TestBackend {
+        amount_offset: u64,
+        gas_offset: u64,
+        price_offset: u64,
+        ticks_crossed: u32,
+        partial_fill_from: Option<U256>,
+        reverts_at: Option<U256>,
+        revert_reason: &'static str,
+    }
+
+    impl TestBackend {
+        fn new() -> Self {
+            Self {
+                amount_offset: 0,
+                gas_offset: 0,
+                price_offset: 0,
+                ticks_crossed: 0,
+                partial_fill_from: None,
+                reverts_at: None,
+                revert_reason: "SPL",
+            }
+        }
+    }
+
+    impl QuoteBackend for TestBackend {
+        async fn quote(&self, request: QuoteRequest) -> anyhow::Result<QuoteResult> {
+            if request.amount_in.is_zero() || Some(request.amount_in) == self.reverts_at {
+                return Err(SimError::Revert {
+                    output: Revert::from(self.revert_reason).abi_encode().into(),
+                    reason: Some(self.revert_reason.to_string()),
+                    gas_used: 0,
+                }
+                .into());
+            }
+            Ok(QuoteResult {
+                amount_out: request.amount_in + U256::from(self.amount_offset),
+                sqrt_price_x96_after: U160::from(1_000 + self.price_offset),
+                ticks_crossed: self.ticks_crossed,
+                gas_estimate: U256::from(100_000 + self.gas_offset),
+                partial_fill: self
+                    .partial_fill_from
+                    .is_some_and(|from| request.amount_in >= from),
+            })
+        }
+    }
+
+    /// Fails without revert data, like the native math.
+    struct NativeLikeBackend;
+
+    impl QuoteBackend for NativeLikeBackend {
+        async fn quote(&self, request: QuoteRequest) -> anyhow::Result<QuoteResult> {
+            anyhow::ensure!(!request.amount_in.is_zero(), "zero amount");
+            TestBackend::new().quote(request).await
+        }
+    }
+
+    /// Fails to reach the state.
+    struct OfflineBackend;
+
+    impl QuoteBackend for OfflineBackend {
+        async fn quote(&self, _request: QuoteRequest) -> anyhow::Result<QuoteResult> {
+            Err(SimError::Database("connection refused".into()).into())
+        }
+    }
+
+    fn requests(amounts: &[u64]) -> Vec<QuoteRequest> {
+        amounts
+            .iter()
+            .map(|amount| {
+                QuoteRequest::new(Address::ZERO, Address::ZERO, 3000, U256::from(*amount))
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn accepts_gas_within_tolerance() {
+        let candidate = TestBackend {
+            gas_offset: 40,
+            ..TestBackend::new()
+        };
+        let requests = requests(&[1, 2, 3]);
+
+        let report = verify(&TestBackend::new(), &candidate, &requests, Some(40))
+            .await
+            .unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.quotes, 3);
+        assert_eq!(report.max_gas_delta, U256::from(40));
+
+        let report = verify(&TestBackend::new(), &candidate, &requests, Some(39))
+            .await
+            .unwrap();
+        assert_eq!(report.divergences.len(), 3);
+        assert_eq!(
+            report.divergences[0].mismatches,
+            [Mismatch::GasEstimate {
+                expected: U256::from(100_000),
+                actual: U256::from(100_040),
+            }]
+        );
+
+        let report = verify(&TestBackend::new(), &candidate, &requests, None)
+            .await
+            .unwrap();
+        assert!(report.is_ok());
+    }
+
+    #[tokio::test]
+    async fn reports_amount_mismatches() {
+        let candidate = TestBackend {
+            amount_offset: 1,
+            ..TestBackend::new()
+        };
+        let report = verify(&TestBackend::new(), &candidate, &requests(&[5]), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            report.divergences[0].mismatches,
+            [Mismatch::AmountOut {
+                expected: U256::from(5),
+                actual: U256::from(6),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_revert_mismatches() {
+        let candidate = TestBackend {
+            reverts_at: Some(U256::from(7)),
+            ..TestBackend::new()
+        };
+        let report = verify(&TestBackend::new(), &candidate, &requests(&[0, 7, 8]), None)
+            .await
+            .unwrap();
+
+        // Both revert on zero, only the candidate on 7.
+        assert_eq!(report.reverts, 1);
+        assert_eq!(report.quotes, 1);
+        assert_eq!(report.divergences.len(), 1);
+        assert_eq!(report.divergences[0].request.amount_in, U256::from(7));
+        assert_eq!(
+            report.divergences[0].mismatches,
+            [Mismatch::Revert {
+                expected: None,
+                actual: Some("execution reverted: SPL".to_string()),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn fails_on_infrastructure_errors() {
+        let result = verify(&TestBackend::new(), &OfflineBackend, &requests(&[1]), None).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn reports_revert_reason_mismatches() {
+        let candidate = TestBackend {
+            revert_reason: "AS",
+            ..TestBackend::new()
+        };
+        let report = verify(&TestBackend::new(), &candidate, &requests(&[0, 1]), None)
+            .await
+            .unwrap();
+
+        assert_eq!(report.reverts, 1);
+        assert_eq!(
+            report.divergences[0].mismatches,
+            [Mismatch::RevertReason {
+                expected: "execution reverted: SPL".to_string(),
+                actual: "execution reverted: AS".to_string(),
+            }]
+        );
+
+        // The same revert data matches even if the errors read differently.
+        let report = verify(
+            &TestBackend::new(),
+            &TestBackend::new(),
+            &requests(&[0]),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(report.is_ok());
+        // Without revert data only the failure itself is compared.
+        let report = verify(
+            &TestBackend::new(),
+            &NativeLikeBackend,
+            &requests(&[0, 1]),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(report.is_ok());
+        assert_eq!(report.reverts, 1);
+    }
+
+    #[tokio::test]
+    async fn reports_price_after_mismatches() {
+        let candidate = TestBackend {
+            price_offset: 1,
+            ..TestBackend::new()
+        };
+        let report = verify(&TestBackend::new(), &candidate, &requests(&[5]), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            report.divergences[0].mismatches,
+            [Mismatch::SqrtPriceAfter {
+                expected: U160::from(1_000),
+                actual: U160::from(1_001),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_partial_fill_mismatches() {
+        let reference = TestBackend {
+            partial_fill_from: Some(U256::from(10)),
+            ..TestBackend::new()
+        };
+        let candidate = TestBackend {
+            partial_fill_from: Some(U256::from(20)),
+            ..TestBackend::new()
+        };
+        let report = verify(&reference, &candidate, &requests(&[5, 15, 25]), None)
+            .await
+            .unwrap();
+
+        assert_eq!(report.divergences.len(), 1);
+        assert_eq!(report.divergences[0].request.amount_in, U256::from(15));
+        assert_eq!(
+            report.divergences[0].mismatches,
+            [Mismatch::PartialFill {
+                expected: true,
+                actual: false,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn compares_ticks_crossed_with_gas() {
+        let candidate = TestBackend {
+            ticks_crossed: 1,
+            ..TestBackend::new()
+        };
+        let report = verify(&TestBackend::new(), &candidate, &requests(&[5]), Some(0))
+            .await
+            .unwrap();
+        assert_eq!(
+            report.divergences[0].mismatches,
+            [Mismatch::TicksCrossed {
+                expected: 0,
+                actual: 1,
+            }]
+        );
+
+        let report = verify(&TestBackend::new(), &candidate, &requests(&[5]), None)
+            .await
+            .unwrap();
+        assert!(report.is_ok());
+    }
+}
@@ -0,0 +1,166 @@
+//! `verify` of revm against `eth_call` on a local non-forked Anvil with mock contracts.
+//!
+//! The mocks from `src/test_support.rs` are assembled by hand so the test only needs
+//! `anvil` (ignored by default, run it with `cargo test -- --ignored`; the mocks
+//! themselves are also checked in revm):
+//! an ERC20 with the Solidity storage layout, a pool that pays out 99.7% of the amount in
+//! at a constant price and a Quoter V2 that reverts with the amount out from the swap callback.
+
+use std::sync::Arc;
+
+use alloy::{
+    network::TransactionBuilder,
+    node_bindings::Anvil,
+    primitives::{Address, B256, Bytes, U256, keccak256},
+    providers::{Provider, ProviderBuilder, ext::AnvilApi},
+    rpc::types::TransactionRequest,
+    sol_types::SolValue,
+};
+use denegnet::{
+    cache::EvmCache,
+    erc20::set_erc20_balance,
+    quote::{EthCallBackend, PoolKey, QuoteBackend, QuoteRequest, RevmBackend},
+    revm::{RevmProvider, Simulator},
+    verify::verify,
+};
+use revm::database::{CacheDB, EmptyDB};
+
+#[path = "../src/test_support.rs"]
+mod test_support;
+
+use test_support::{HashOrder, init_code, insert_code, pool_code, quoter_code};
+
+/// Solidity layout, balances at slot 0.
+fn token_code() -> Bytes {
+    test_support::token_code(HashOrder::KeyFirst, 0, 1)
+}
+
+async fn deploy(provider: &RevmProvider, from: Address, runtime: Bytes) -> Address {
+    let tx = TransactionRequest::default()
+        .from(from)
+        .with_deploy_code(init_code(&runtime));
+    let receipt = provider
+        .send_transaction(tx)
+        .await
+        .unwrap()
+        .get_receipt()
+        .await
+        .unwrap();
+    assert!(receipt.status(), "deployment failed");
+    receipt.contract_address.unwrap()
+}
+
+async fn set_storage(provider: &RevmProvider, address: Address, slot: U256, value: U256) {
+    provider
+        .anvil_set_storage_at(address, slot, B256::from(value))
+        .await
+        .unwrap();
+}
+
+/// The mocks on their own, so they are checked even without `anvil`.
+#[test]
+fn mocks_quote_in_revm() {
+    let (token_a, token_b) = (Address::repeat_byte(0x0a), Address::repeat_byte(0x0b));
+    let (pool, quoter) = (Address::repeat_byte(0x50), Address::repeat_byte(0x51));
+
+    let mut db = CacheDB::new(EmptyDB::default());
+    insert_code(&mut db, token_a, token_code());
+    insert_code(&mut db, token_b, token_code());
+    insert_code(&mut db, pool, pool_code());
+    insert_code(&mut db, quoter, quoter_code());
+    db.insert_account_storage(pool, U256::ZERO, token_a.into_word().into())
+        .unwrap();
+    db.insert_account_storage(pool, U256::from(1), token_b.into_word().into())
+        .unwrap();
+    db.insert_account_storage(quoter, U256::ZERO, pool.into_word().into())
+        .unwrap();
+
+    let mut simulator = Simulator::new(db);
+    for token in [token_a, token_b] {
+        set_erc20_balance(&mut simulator, token, pool, U256::from(1_000_000)).unwrap();
+    }
+
+    for (token_in, token_out) in [(token_a, token_b), (token_b, token_a)] {
+        let request = QuoteRequest::new(token_in, token_out, 3000, U256::from(10_000));
+        let response = simulator.call(quoter, request.calldata()).unwrap();
+        let quote = request.decode(response).unwrap();
+        assert_eq!(quote.amount_out, U256::from(9_970));
+        assert!(quote.gas_estimate > U256::ZERO);
+        assert!(!quote.partial_fill);
+    }
+
+    // Zero amounts and amounts the pool can't pay out revert.
+    for amount in [0, 2_000_000] {
+        let request = QuoteRequest::new(token_a, token_b, 3000, U256::from(amount));
+        assert!(simulator.call(quoter, request.calldata()).is_err());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "requires anvil"]
+async fn revm_matches_eth_call_on_local_anvil() {
+    let anvil = Anvil::new().try_spawn().unwrap();
+    let provider: RevmProvider =
+        Arc::new(ProviderBuilder::new().connect_http(anvil.endpoint_url()));
+    let deployer = anvil.addresses()[0];
+
+    let token_a = deploy(&provider, deployer, token_code()).await;
+    let token_b = deploy(&provider, deployer, token_code()).await;
+    let pool = deploy(&provider, deployer, pool_code()).await;
+    let quoter = deploy(&provider, deployer, quoter_code()).await;
+
+    let pool_key = PoolKey::new(pool, token_a, token_b, 3000);
+    set_storage(
+        &provider,
+        pool,
+        U256::ZERO,
+        pool_key.token0.into_word().into(),
+    )
+    .await;
+    set_storage(
+        &provider,
+        pool,
+        U256::from(1),
+        pool_key.token1.into_word().into(),
+    )
+    .await;
+    set_storage(&provider, quoter, U256::ZERO, pool.into_word().into()).await;
+    let pool_balance = keccak256((pool, U256::ZERO).abi_encode());
+    for token in [token_a, token_b] {
+        let balance = U256::from(10).pow(U256::from(24));
+        set_storage(&provider, token, pool_balance.into(), balance).await;
+    }
+    // Both backends read the state at a block mined after the setup.
+    provider.anvil_mine(Some(1), None).await.unwrap();
+    let block = provider.get_block_number().await.unwrap();
+
+    let reference = EthCallBackend::new(provider.clone(), block).with_quoter(quoter);
+    let dir = std::env::temp_dir().join(format!("denegnet-verify-{}", std::process::id()));
+    let cache = EvmCache::new(&dir, provider.get_chain_id().await.unwrap());
+    let candidate = RevmBackend::prewarmed(provider.clone(), block, cache, &[pool_key])
+        .await
+        .unwrap()
+        .with_quoter(quoter);
+
+    let requests: Vec<_> = [0_u64, 1_000, 1_000_000_000_000_000]
+        .into_iter()
+        .flat_map(|amount| {
+            [(token_a, token_b), (token_b, token_a)].map(|(token_in, token_out)| {
+                QuoteRequest::new(token_in, token_out, 3000, U256::from(amount))
+            })
+        })
+        .collect();
+
+    let quote = reference.quote(requests[2]).await.unwrap();
+    assert_eq!(quote.amount_out, U256::from(997));
+
+    let report = verify(&reference, &candidate, &requests, Some(0))
+        .await
+        .unwrap();
+    std::fs::remove_dir_all(dir).ok();
+
+    assert!(report.is_ok(), "{:?}", report.divergences);
+    assert_eq!(report.quotes, 4);
+    // The pool rejects zero amounts.
+    assert_eq!(report.reverts, 2);
+}